@@ -0,0 +1,101 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::time::Instant;
+
+use crate::github::trace_response;
+
+/// Where a contest's report and leaderboard are published once it's judged, followed by its slug.
+pub const AUDITS_PAGE_URL: &str = "https://code4rena.com/audits";
+
+/// Fields a leaderboard entry gives its payout under, in USD.
+const AWARD_FIELDS: [&str; 4] = ["awardUSD", "award_usd", "totalAward", "award"];
+
+/// What a judged contest actually paid out, read from its leaderboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwardSummary {
+    pub url: String,
+    /// Sum of every payout, in USD
+    pub total_awarded: f64,
+    /// Largest payout first
+    pub payouts: Vec<Payout>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    /// Warden or team handle
+    pub handle: String,
+    pub amount: f64,
+}
+
+/// Fetches the leaderboard of the contest `slug`, `None` when the page holds none yet, e.g. while
+/// the contest is still being judged.
+pub async fn get_awards(client: &reqwest::Client, slug: &str) -> Result<Option<AwardSummary>, reqwest::Error> {
+    let url = format!("{}/{}", AUDITS_PAGE_URL, slug);
+    let started = Instant::now();
+    let response = client.get(&url).send().await?;
+    trace_response(&url, &response, started.elapsed());
+    let page = response.error_for_status()?.text().await?;
+
+    Ok(parse_awards(&page).map(|payouts| AwardSummary {
+        url,
+        total_awarded: payouts.iter().map(|payout| payout.amount).sum(),
+        payouts,
+    }))
+}
+
+/// Pulls the leaderboard out of an audit page's Next.js script chunks, every object with a `handle`
+/// and a payout counts, wherever the page nests it. A handle listed more than once keeps its first payout,
+/// pages render the same leaderboard in several places.
+pub fn parse_awards(html: &str) -> Option<Vec<Payout>> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("script").unwrap();
+
+    let mut payouts: Vec<Payout> = Vec::new();
+    for script in document.select(&selector).map(|script| script.inner_html()) {
+        // Each chunk is `self.__next_f.push([1,"<id>:<json>\n..."])`, the string is JSON escaped
+        let Some(chunk) = script.strip_prefix("self.__next_f.push(").and_then(|chunk| chunk.strip_suffix(')')) else {
+            continue;
+        };
+        let Ok(Value::Array(chunk)) = serde_json::from_str::<Value>(chunk) else {
+            continue;
+        };
+        let Some(Value::String(rows)) = chunk.get(1) else {
+            continue;
+        };
+        for row in rows.lines() {
+            let Some((_, payload)) = row.split_once(':') else {
+                continue;
+            };
+            if let Ok(payload) = serde_json::from_str::<Value>(payload) {
+                collect_payouts(&payload, &mut payouts);
+            }
+        }
+    }
+    if payouts.is_empty() {
+        return None;
+    }
+
+    payouts.sort_by(|a, b| b.amount.total_cmp(&a.amount).then_with(|| a.handle.cmp(&b.handle)));
+    Some(payouts)
+}
+
+fn collect_payouts(value: &Value, payouts: &mut Vec<Payout>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|value| collect_payouts(value, payouts)),
+        Value::Object(object) => {
+            let handle = object.get("handle").and_then(Value::as_str);
+            let amount = AWARD_FIELDS.iter().find_map(|field| object.get(*field).and_then(Value::as_f64));
+            match (handle, amount) {
+                (Some(handle), Some(amount)) => {
+                    if !payouts.iter().any(|payout| payout.handle == handle) {
+                        payouts.push(Payout { handle: handle.to_string(), amount });
+                    }
+                }
+                _ => object.values().for_each(|value| collect_payouts(value, payouts)),
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::path::{Path, PathBuf};
+
+/// GitHub response body alongside the validators it was served with, at least one of them is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// `Last-Modified`, sent back as `If-Modified-Since`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+/// On-disk cache of GitHub responses keyed by url, revalidated with `If-None-Match` and `If-Modified-Since`.
+///
+/// A `304 Not Modified` doesn't count against the rate limit, so unchanged trees and blobs
+/// are close to free on repeated runs.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        let dir = dir.join("responses");
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(ResponseCache { dir })
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(Sha256::digest(url.as_bytes()))))
+    }
+
+    /// The cached response for `url`, unreadable entries count as missing.
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let entry = std::fs::read(self.entry_path(url)).ok()?;
+        serde_json::from_slice(&entry).ok()
+    }
+
+    pub fn put(&self, url: &str, response: &CachedResponse) -> std::io::Result<()> {
+        // Write then rename so concurrent downloads never see a half written entry
+        let mut entry = tempfile::NamedTempFile::new_in(&self.dir)?;
+        serde_json::to_writer(&mut entry, response)?;
+        entry.persist(self.entry_path(url))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{bail, Context, Result};
+use sha1::{Digest, Sha1};
+use tokio::process::Command;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::github::RepoRef;
+use crate::sources::{is_source_file, RepoSources, SourceExtension};
+
+/// Shallow clones `repo` from GitHub, submodules included, and reads every file with one of `extensions`
+/// outside of `skip_dirs`.
+///
+/// One clone replaces the tree request and the per-file blob requests, the checkout is removed
+/// once the sources are read.
+pub async fn clone_sources(repo: &RepoRef, extensions: &[SourceExtension], skip_dirs: &[String]) -> Result<RepoSources> {
+    let checkout = tempfile::tempdir()?;
+    let url = repo.url();
+    eprintln!("Cloning {}", url);
+
+    match &repo.reference {
+        None => {
+            let destination = checkout.path().to_string_lossy();
+            git(
+                Path::new("."),
+                &["clone", "--quiet", "--depth", "1", "--recurse-submodules", "--shallow-submodules", &url, &destination],
+            )
+            .await?;
+        }
+        Some(reference) => {
+            // `clone --branch` can't take a commit, fetching a single ref works for branches, tags and SHAs alike
+            let directory = checkout.path();
+            git(directory, &["init", "--quiet"]).await?;
+            git(directory, &["fetch", "--quiet", "--depth", "1", &url, reference]).await?;
+            git(directory, &["checkout", "--quiet", "FETCH_HEAD"]).await?;
+            git(directory, &["submodule", "update", "--quiet", "--init", "--recursive", "--depth", "1"]).await?;
+        }
+    }
+
+    let mut sources = RepoSources::default();
+    read_sources(checkout.path(), checkout.path(), extensions, skip_dirs, &mut sources)?;
+    sources.commit = Some(head_commit(checkout.path()).await?);
+    sources.foundry_toml = std::fs::read_to_string(checkout.path().join("foundry.toml")).ok();
+
+    Ok(sources)
+}
+
+/// Unpacks a GitHub tarball and reads every file with one of `extensions` outside of `skip_dirs`, like
+/// `clone_sources` does.
+///
+/// Tarballs leave submodules out, dependencies vendored that way won't be among the sources.
+pub fn extract_tarball(tarball: &[u8], extensions: &[SourceExtension], skip_dirs: &[String]) -> Result<RepoSources> {
+    let extracted = tempfile::tempdir()?;
+    tar::Archive::new(flate2::read::GzDecoder::new(tarball))
+        .unpack(extracted.path())
+        .context("Failed to unpack the tarball")?;
+    // Everything sits under a single `<owner>-<repo>-<sha>/` directory
+    let root = std::fs::read_dir(extracted.path())?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path())
+        .context("The tarball has no top-level directory")?;
+
+    let mut sources = RepoSources::default();
+    read_sources(&root, &root, extensions, skip_dirs, &mut sources)?;
+    sources.foundry_toml = std::fs::read_to_string(root.join("foundry.toml")).ok();
+
+    Ok(sources)
+}
+
+/// The commit `repo`'s reference (or HEAD) points at on the remote, without cloning anything.
+pub async fn resolve_commit(repo: &RepoRef) -> Result<String> {
+    let reference = repo.reference.as_deref().unwrap_or("HEAD");
+    // ls-remote only lists refs, a full SHA is already as pinned as it gets
+    if reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(reference.to_string());
+    }
+
+    // ls-remote matches any ref ending in a pattern, so only exact names are taken from its listing.
+    // A branch wins over a tag, and an annotated tag is the commit it points to as `<tag>^{}`
+    let wanted: Vec<String> = if reference == "HEAD" || reference.starts_with("refs/") {
+        vec![format!("{}^{{}}", reference), reference.to_string()]
+    } else {
+        vec![format!("refs/heads/{}", reference), format!("refs/tags/{}^{{}}", reference), format!("refs/tags/{}", reference)]
+    };
+    // A peeled `<tag>^{}` is only listed when asked for by that name
+    let output = Command::new("git")
+        .arg("ls-remote")
+        .arg(repo.url())
+        .args(&wanted)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git ls-remote of {} exited with {}", repo.url(), output.status);
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let refs: BTreeMap<&str, &str> = listing.lines().filter_map(|line| line.split_once('\t')).map(|(sha, name)| (name, sha)).collect();
+    wanted
+        .iter()
+        .find_map(|name| refs.get(name.as_str()))
+        .map(|sha| sha.to_string())
+        .with_context(|| format!("{} has no ref {}", repo.url(), reference))
+}
+
+async fn head_commit(directory: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(directory)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git rev-parse HEAD exited with {}", output.status);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The SHA git names a blob with, `sha1("blob <len>\0" + content)`.
+pub fn blob_sha(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()));
+    hasher.update(content);
+    hex::encode(hasher.finalize())
+}
+
+async fn git(directory: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .status()
+        .await
+        .context("Failed to run git")?;
+    if !status.success() {
+        bail!("git {} exited with {}", args.join(" "), status);
+    }
+
+    Ok(())
+}
+
+fn read_sources(root: &Path, directory: &Path, extensions: &[SourceExtension], skip_dirs: &[String], sources: &mut RepoSources) -> Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            // The checkout's own `.git` is never a source, whatever `skip_dirs` says
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name != ".git" && !skip_dirs.contains(&name) {
+                read_sources(root, &path, extensions, skip_dirs, sources)?;
+            }
+        } else if file_type.is_file() && is_source_file(&entry.file_name().to_string_lossy(), extensions) {
+            let relative = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let content = std::fs::read(&path)?;
+            sources.blob_shas.insert(relative.clone(), blob_sha(&content));
+            sources.files.insert(relative, String::from_utf8_lossy(&content).into_owned());
+        }
+    }
+
+    Ok(())
+}
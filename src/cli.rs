@@ -0,0 +1,451 @@
+use clap::{Parser, Subcommand, ValueEnum};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use code4rena::compile::{Artifact, CompileSettings};
+use code4rena::contests::{ContestFilter, Trust};
+use code4rena::output::Compression;
+use code4rena::prices::UsdPrices;
+use code4rena::priority::PriorityWeights;
+use code4rena::sources::{SourceExtension, SKIPPED_DIRS};
+
+/// Concurrent GitHub downloads when `--threads` is not given.
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// Scrapes active code4rena contests and compiles their contracts
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Contests processed at the same time, each with its own download concurrency, GitHub rate
+    /// limits still pause them all together
+    #[arg(long, alias = "max-contests-concurrency", value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub contest_concurrency: u16,
+
+    /// Bounds both concurrent downloads and the compile thread pool, 1 runs everything sequentially
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: Option<u16>,
+
+    /// Compile this many targets at a time, each batch's compiler output is dropped before the next
+    /// starts and with `--format jsonl` its records are written straight away
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub batch_size: Option<u16>,
+
+    /// Print the serde error and raw JSON of every contest entry that fails to parse
+    #[arg(long)]
+    pub debug_json: bool,
+
+    /// Proxy for all requests, overrides HTTPS_PROXY/HTTP_PROXY
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Cache GitHub responses here and revalidate them with their ETag or Last-Modified on later runs,
+    /// unchanged blobs come back as a bodyless 304
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Log every HTTP request's URL, status, duration and remaining rate-limit quota to stderr
+    #[arg(long)]
+    pub trace_requests: bool,
+
+    /// Write every tracing event of the run to this file as JSON lines, requests, rate-limit waits and
+    /// retries included, whatever stdout and stderr show
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Milliseconds between the starts of GitHub requests, shared by all concurrent downloads so
+    /// they make at most `1000/MS` requests a second together
+    #[arg(long, value_name = "MS")]
+    pub request_delay: Option<u64>,
+
+    /// Total time all GitHub retries may wait for across the run, e.g. `10m`, the run stops once it's spent
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub max_total_retry_time: Option<std::time::Duration>,
+
+    /// Where contests are read from, `api` falls back to the html page when the endpoint fails
+    #[arg(long, value_enum, default_value_t = ContestSource::Html)]
+    pub source: ContestSource,
+
+    /// Contests page (or endpoint with `--source api`) to read, repeatable, contests listed by
+    /// several are kept once. Defaults to code4rena's
+    #[arg(long, value_name = "URL")]
+    pub url: Vec<String>,
+
+    /// Read contests from a saved contests JSON blob instead of scraping code4rena
+    #[arg(long, value_name = "FILE")]
+    pub contests_from: Option<PathBuf>,
+
+    /// Only process the contests whose id or slug is listed in this file, one per line, `#` starts
+    /// a comment line
+    #[arg(long, value_name = "FILE")]
+    pub contest_ids_file: Option<PathBuf>,
+
+    /// Also keep contests that ended within this long ago, e.g. `7d`
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub include_ended_within: Option<std::time::Duration>,
+
+    /// Also read the leaderboard of every contest that already ended into a payout summary, one extra
+    /// request per ended contest. Pair with `--include-ended-within` to reach past contests
+    #[arg(long)]
+    pub fetch_awards: bool,
+
+    /// Whether a contest's `status` or its `end_time` decides if it's active when the two disagree
+    #[arg(long, value_enum, default_value_t = Trust::Time)]
+    pub trust: Trust,
+
+    /// Skip contests whose code access isn't public, their repos 404 without an approved application
+    #[arg(long, value_name = "BOOL", default_value_t = true, action = clap::ArgAction::Set)]
+    pub public_only: bool,
+
+    /// Normalize every award pool to USD, stablecoins at 1.0 and other coins at their `--prices` price.
+    /// `--min-award` and `--sort-by award` then compare USD
+    #[arg(long)]
+    pub usd: bool,
+
+    /// JSON object of award coin to USD price, e.g. `{"ETH": 3000}`, for `--usd`
+    #[arg(long, value_name = "FILE", requires = "usd")]
+    pub prices: Option<PathBuf>,
+
+    /// Only process contests with one of these tags, from the contests page or the `Tags:` line of the
+    /// repo's README, case-insensitive and repeatable
+    #[arg(long, value_name = "NAME")]
+    pub tag: Vec<String>,
+
+    /// Skip contests whose award pool is smaller, in USD with `--usd` and the pool's own coin otherwise
+    #[arg(long, value_name = "AMOUNT")]
+    pub min_award: Option<f64>,
+
+    /// Order of the contests in the summary and `--output`
+    #[arg(long, value_enum, default_value_t = SortBy::Page)]
+    pub sort_by: SortBy,
+
+    /// Weight of award per SLOC in the priority score
+    #[arg(long, value_name = "WEIGHT", default_value_t = 1.0)]
+    pub award_weight: f64,
+
+    /// Weight of scope size in the priority score, smaller scopes rank higher
+    #[arg(long, value_name = "WEIGHT", default_value_t = 1.0)]
+    pub scope_weight: f64,
+
+    /// Weight of time remaining in the priority score
+    #[arg(long, value_name = "WEIGHT", default_value_t = 1.0)]
+    pub time_weight: f64,
+
+    /// Remember the last processed commit of every repo and every completed file here between runs,
+    /// a rerun skips files already done at the same blob SHA
+    #[arg(long, value_name = "FILE")]
+    pub state_file: Option<PathBuf>,
+
+    /// Skip repos whose head commit hasn't moved since the last run recorded in `--state-file`
+    #[arg(long, requires = "state_file")]
+    pub only_changed: bool,
+
+    /// Skip contests an earlier run recorded in `--state-file` as fully processed, every repo at the
+    /// commit its head is at now. Nothing of a skipped contest is fetched but the head commits
+    #[arg(long, requires = "state_file")]
+    pub skip_existing: bool,
+
+    /// Mark every target `new`, `changed` or `unchanged` against the blob SHAs `--state-file`
+    /// recorded last run, every target is compiled again to be reported
+    #[arg(long, requires = "state_file")]
+    pub compare_with_previous: bool,
+
+    /// Flag in-scope files that are unmodified copies of these libraries, a JSON object of git blob
+    /// SHA to label or a library checkout to hash, repeatable. Copies of a repo's own `lib/` sources
+    /// are always flagged, and its `node_modules/` ones when `--skip-dir` lets them be fetched
+    #[arg(long, value_name = "PATH")]
+    pub known_libraries: Vec<PathBuf>,
+
+    /// Print every compiler warning, not just the per-file count
+    #[arg(long)]
+    pub show_warnings: bool,
+
+    /// Treat compiler warnings as errors: every warning is printed, a file with any is `failed`, and
+    /// the run exits non-zero once the results are written
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Write the results to this file, with `--format json` this is the input of the `diff` subcommand
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Also write every emitted ABI of the run to this file, one JSON object keyed by contract name,
+    /// or `<path>:<name>` for names declared more than once
+    #[arg(long, value_name = "FILE")]
+    pub combined_abi: Option<PathBuf>,
+
+    /// Group `--out-dir` under a directory per sponsor and `--format json` under a `sponsors` object
+    /// keyed by sponsor name
+    #[arg(long, value_enum, value_name = "KEY")]
+    pub group_by: Option<GroupBy>,
+
+    /// Shape of the results written to `--output`
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    pub format: Format,
+
+    /// Compress `--output`, `diff` reads compressed results too
+    #[arg(long, value_enum, value_name = "CODEC")]
+    pub compress: Option<Compression>,
+
+    /// Save every fetched source of a repo to `<out-dir>/<contest>/sources.tar.zst`
+    #[arg(long, requires = "out_dir")]
+    pub save_sources: bool,
+
+    /// Source extensions to fetch and compile, `.sol` and `.yul` go to solc and `.vy` to vyper
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [SourceExtension::Sol])]
+    pub ext: Vec<SourceExtension>,
+
+    /// Directories never fetched at any depth, build output and the like. Giving any replaces the
+    /// default set, `--skip-dir ''` skips nothing
+    #[arg(long, value_name = "DIR", default_values_t = SKIPPED_DIRS.map(String::from))]
+    pub skip_dir: Vec<String>,
+
+    /// Shallow clone each contest repo with git instead of fetching files through the GitHub API
+    #[arg(long)]
+    pub git_clone: bool,
+
+    /// Download each repo as one tarball through the GitHub API instead of file by file, submodules
+    /// aren't included
+    #[arg(long, conflicts_with = "git_clone")]
+    pub tarball: bool,
+
+    /// Directory for per-contest artifacts
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Compiler outputs to select and report, `ast` needs `--out-dir`
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [Artifact::Abi, Artifact::Bytecode])]
+    pub emit: Vec<Artifact>,
+
+    /// Encoding of the emitted bytecode, `raw` writes `<out-dir>/<contest>/bytecode/<path>/<contract>.bin` files
+    #[arg(long, value_enum, default_value_t = BytecodeFormat::Hex)]
+    pub bytecode_format: BytecodeFormat,
+
+    /// Write the standard JSON input of each target under `<out-dir>/<contest>/standard-json/`
+    #[arg(long, requires = "out_dir")]
+    pub emit_standard_json: bool,
+
+    /// Leave interfaces, abstract contracts and libraries out of the results, keeping only concrete
+    /// contracts with creation code
+    #[arg(long)]
+    pub contracts_only: bool,
+
+    /// Check every emitted bytecode is non-empty, even-length and decodable hex, and fail the run
+    /// once the results are written if any isn't
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Compile everything through the IR pipeline, repos whose foundry.toml sets `via_ir` use it regardless
+    #[arg(long)]
+    pub via_ir: bool,
+
+    /// Compile with this solc binary instead of the one found on PATH, or the svm release each target's
+    /// pragmas allow
+    #[arg(long, value_name = "PATH")]
+    pub solc_path: Option<PathBuf>,
+
+    /// Don't install the solc releases a repo's pragmas need before compiling it. Targets compile with
+    /// the one svm already has, or the solc on PATH
+    #[arg(long)]
+    pub skip_prewarm: bool,
+
+    /// Compile every file with the solc version `<out-dir>/solc.lock` recorded for it, failing when
+    /// the solc in use is a different one. Every run with `--out-dir` updates the lock
+    #[arg(long, requires = "out_dir")]
+    pub locked: bool,
+
+    /// Link external libraries at these addresses, `NAME=0xADDRESS` or a JSON file of name to address,
+    /// repeatable. Contracts needing a library without one are reported and keep no bytecode
+    #[arg(long, value_name = "NAME=ADDRESS|FILE", value_parser = parse_libraries)]
+    pub libraries: Vec<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ContestSource {
+    /// Scrape the contests page's embedded Next.js payload
+    Html,
+    /// Deserialize the structured JSON endpoint
+    Api,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// As listed on the contests page
+    Page,
+    /// Highest `priority::priority_score` first
+    Priority,
+    /// Largest award pool in USD first, needs `--usd`
+    Award,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A single versioned document, written once the run finishes
+    Json,
+    /// One object per contract, streamed to `--output` or stdout as each batch completes
+    Jsonl,
+    /// An aligned table of the contests, to `--output` or stdout
+    Table,
+    /// Just every contest's in-scope files and their sizes, to `--output` or stdout, nothing is compiled
+    Index,
+    /// A GitHub Actions `::error` annotation on stdout for every compile error, and `::warning` for
+    /// warnings with `--show-warnings`
+    GithubActions,
+    /// A Markdown scope report per contest, its details and a table of the in-scope files with their SLOC
+    /// and contracts, written to `<out-dir>/<contest>.md`
+    Markdown,
+    /// One line per contest with its sponsor, award and time left, split into Discord messages of at most
+    /// 2000 characters, to `--output` or stdout. Nothing is fetched or compiled
+    Discord,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// The sponsor's name, lowercased and made directory-safe
+    Sponsor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BytecodeFormat {
+    /// Bare hex
+    Hex,
+    /// `0x` prefixed hex
+    #[value(name = "0xhex")]
+    PrefixedHex,
+    /// Binary `.bin` files, left out of the results
+    Raw,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Report new, ended, and changed contests between two `--output` files
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Print the differences as JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compile a contract at two refs of a repo and compare its bytecode, metadata stripped
+    BytecodeDiff {
+        /// The repo on GitHub, as `owner/name`, its URL or its `git@github.com:` SSH URL
+        #[arg(long)]
+        repo: String,
+
+        /// Branch, tag or commit before the change
+        #[arg(long, value_name = "REF")]
+        from: String,
+
+        /// Branch, tag or commit after the change
+        #[arg(long, value_name = "REF")]
+        to: String,
+
+        /// Name of the contract to compare
+        #[arg(long, value_name = "NAME")]
+        contract: String,
+
+        /// Print the comparison as JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fetch and compile a single GitHub repo instead of the active contests
+    Repo {
+        /// The repo on GitHub, as `owner/name`, its URL or its `git@github.com:` SSH URL
+        repo: String,
+
+        /// Branch or tag to compile, defaults to the repo's default branch
+        #[arg(long = "ref", value_name = "REF", conflicts_with = "commit")]
+        reference: Option<String>,
+
+        /// Commit to compile
+        #[arg(long, value_name = "SHA")]
+        commit: Option<String>,
+    },
+    /// Check an install offline: parse a bundled contests page and compile a bundled contract,
+    /// printing pass or fail for each stage
+    Selftest,
+    /// Scrape the active contests periodically and serve the latest results as JSON over HTTP,
+    /// at `/contests` and `/contests/<id>/contracts`
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: std::net::SocketAddr,
+
+        /// Time between the end of one scrape and the start of the next, e.g. `30m`
+        #[arg(long, value_name = "DURATION", default_value = "1h", value_parser = humantime::parse_duration)]
+        interval: std::time::Duration,
+    },
+}
+
+impl Cli {
+    pub fn download_concurrency(&self) -> usize {
+        self.threads.map_or(DEFAULT_DOWNLOAD_CONCURRENCY, usize::from)
+    }
+
+    /// Size of the rayon compile pool, defaults to one thread per core.
+    pub fn compile_threads(&self) -> usize {
+        self.threads.map_or_else(
+            || std::thread::available_parallelism().map_or(1, |n| n.get()),
+            usize::from,
+        )
+    }
+
+    /// Grace period for contests that already ended, zero unless `--include-ended-within` is given.
+    pub fn ended_within(&self) -> chrono::Duration {
+        self.include_ended_within
+            .and_then(|window| chrono::Duration::from_std(window).ok())
+            .unwrap_or_else(chrono::Duration::zero)
+    }
+
+    pub fn request_delay(&self) -> Option<std::time::Duration> {
+        self.request_delay.map(std::time::Duration::from_millis)
+    }
+
+    pub fn priority_weights(&self) -> PriorityWeights {
+        PriorityWeights { award: self.award_weight, scope: self.scope_weight, time: self.time_weight }
+    }
+
+    /// What `--min-award` and `--tag` select, the pool compared in USD when `prices` are given.
+    pub fn contest_filter(&self, prices: Option<UsdPrices>) -> ContestFilter {
+        let mut filter = ContestFilter::new().tags(self.tag.iter().cloned());
+        if let Some(min_award) = self.min_award {
+            filter = filter.min_award(min_award);
+        }
+        if let Some(prices) = prices {
+            filter = filter.prices(prices);
+        }
+        filter
+    }
+
+    pub fn compile_settings(&self) -> CompileSettings {
+        CompileSettings {
+            emit: self.emit.iter().copied().collect(),
+            solc_path: self.solc_path.clone(),
+            via_ir: self.via_ir,
+            libraries: self.libraries.iter().flatten().map(|(name, address)| (name.clone(), address.clone())).collect(),
+        }
+    }
+}
+
+/// A `--libraries` value, either a single `NAME=0xADDRESS` or the path of a JSON object of them.
+fn parse_libraries(value: &str) -> Result<BTreeMap<String, String>, String> {
+    let libraries = match value.split_once('=') {
+        Some((name, address)) => BTreeMap::from([(name.to_string(), address.to_string())]),
+        None => {
+            let libraries = std::fs::read_to_string(value).map_err(|err| format!("Failed to read {}: {}", value, err))?;
+            serde_json::from_str(&libraries).map_err(|err| format!("Failed to parse {}: {}", value, err))?
+        }
+    };
+    for (name, address) in &libraries {
+        let hex = address.strip_prefix("0x").unwrap_or_default();
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("{} of {} isn't a 0x prefixed 20-byte address", address, name));
+        }
+    }
+
+    Ok(libraries)
+}
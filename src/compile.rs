@@ -0,0 +1,944 @@
+use ethers_solc::{CompilerInput, Solc, CompilerOutput};
+use ethers_solc::artifacts::{Source, Contracts, BytecodeObject, Error, GasEstimates, Libraries, Offsets, Settings, Severity, StandardJsonCompilerInput};
+
+use clap::ValueEnum;
+use rayon::prelude::*;
+use semver::Version;
+use serde_json::Value;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::ScraperError;
+use crate::output::{write_json, ContractArtifact, FileStatus};
+use crate::sources::{contract_kinds, import_closure, solidity_pragmas, Compiler, ContractKind, SourceExtension};
+
+/// Compiler diagnostics reported against a single source file.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub warnings: Vec<Error>,
+    pub errors: Vec<Error>,
+}
+
+impl Diagnostics {
+    /// Keeps the diagnostics located in `path`, plus the ones solc doesn't attach to any file.
+    pub fn for_file(output_errors: &[Error], path: &str) -> Self {
+        let mut diagnostics = Diagnostics::default();
+        for error in output_errors {
+            let in_file = error
+                .source_location
+                .as_ref()
+                .is_none_or(|location| location.file == path);
+            if !in_file {
+                continue;
+            }
+            match error.severity {
+                Severity::Error => diagnostics.errors.push(error.clone()),
+                Severity::Warning => diagnostics.warnings.push(error.clone()),
+                Severity::Info => {}
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// An output solc can be asked for, named after its output selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Artifact {
+    Abi,
+    Bytecode,
+    #[value(name = "storageLayout")]
+    StorageLayout,
+    /// 4-byte selector of every external function
+    #[value(name = "methodIdentifiers", alias = "selectors")]
+    MethodIdentifiers,
+    /// Creation and per-function gas costs
+    #[value(name = "gasEstimates")]
+    GasEstimates,
+    /// Where each immutable sits in the deployed bytecode, to mask them when comparing on-chain code
+    #[value(name = "immutableReferences")]
+    ImmutableReferences,
+    /// Per source unit, written under `<out-dir>/<contest>/ast/`
+    Ast,
+}
+
+impl Artifact {
+    /// The contract level output selection, `None` for the source unit level AST.
+    fn contract_selection(self) -> Option<&'static str> {
+        match self {
+            Artifact::Abi => Some("abi"),
+            Artifact::Bytecode => Some("evm.bytecode"),
+            Artifact::StorageLayout => Some("storageLayout"),
+            Artifact::MethodIdentifiers => Some("evm.methodIdentifiers"),
+            Artifact::GasEstimates => Some("evm.gasEstimates"),
+            Artifact::ImmutableReferences => Some("evm.deployedBytecode.immutableReferences"),
+            Artifact::Ast => None,
+        }
+    }
+}
+
+/// Knobs that change what solc is asked to produce.
+#[derive(Debug, Clone)]
+pub struct CompileSettings {
+    /// Outputs to select, solc skips the work for everything else
+    pub emit: BTreeSet<Artifact>,
+    /// A specific solc binary instead of the one found on PATH
+    pub solc_path: Option<PathBuf>,
+    /// Compile through the IR pipeline, some contracts only fit the stack that way
+    pub via_ir: bool,
+    /// Library name -> address to link it at, see `CompileSettings::linked_libraries`
+    pub libraries: BTreeMap<String, String>,
+}
+
+impl Default for CompileSettings {
+    fn default() -> Self {
+        CompileSettings {
+            emit: BTreeSet::from([Artifact::Abi, Artifact::Bytecode]),
+            solc_path: None,
+            via_ir: false,
+            libraries: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompileSettings {
+    pub fn emits(&self, artifact: Artifact) -> bool {
+        self.emit.contains(&artifact)
+    }
+
+    /// The standard JSON `settings` every compile is run with.
+    pub fn solc_settings(&self) -> Settings {
+        // Replaces the ethers-solc default selection, which always includes the AST and deployed bytecode
+        let contract_selection = self
+            .emit
+            .iter()
+            .filter_map(|artifact| artifact.contract_selection())
+            .map(String::from)
+            .collect();
+        let mut file_selection = BTreeMap::from([("*".to_string(), contract_selection)]);
+        if self.emits(Artifact::Ast) {
+            file_selection.insert(String::new(), vec!["ast".to_string()]);
+        }
+
+        Settings {
+            output_selection: BTreeMap::from([("*".to_string(), file_selection)]).into(),
+            via_ir: self.via_ir.then_some(true),
+            ..Settings::default()
+        }
+    }
+
+    /// The `libraries` declared in `sources`, keyed by the file each is declared in as solc links them.
+    pub fn linked_libraries(&self, sources: &BTreeMap<PathBuf, Source>) -> Libraries {
+        let mut libraries = Libraries::default();
+        if self.libraries.is_empty() {
+            return libraries;
+        }
+        for (path, source) in sources {
+            for (name, kind) in contract_kinds(&source.content) {
+                if let (ContractKind::Library, Some(address)) = (kind, self.libraries.get(&name)) {
+                    libraries.libs.entry(path.clone()).or_default().insert(name, address.clone());
+                }
+            }
+        }
+
+        libraries
+    }
+
+    /// `solc_settings` with the libraries `sources` declare linked in.
+    fn solc_settings_for(&self, sources: &BTreeMap<PathBuf, Source>) -> Settings {
+        Settings { libraries: self.linked_libraries(sources), ..self.solc_settings() }
+    }
+
+    pub fn solc(&self) -> Solc {
+        match &self.solc_path {
+            Some(path) => Solc::new(path),
+            None => Solc::default(),
+        }
+    }
+}
+
+/// A GitHub Actions workflow command that annotates `error` inline, `::error file=..,line=..,col=..::message`.
+///
+/// `source` is the content of the file the error is located in, solc only reports byte offsets.
+pub fn github_annotation(error: &Error, path: &str, source: &str) -> String {
+    let level = match error.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+    let position = error.source_location.as_ref().map(|location| {
+        let before = source.get(..usize::try_from(location.start).unwrap_or(0)).unwrap_or_default();
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+        (line, col)
+    });
+
+    file_annotation(level, path, position, &format!("{}: {}", error.r#type, error.message))
+}
+
+/// A workflow command annotating `path`, at a `(line, col)` when given.
+pub fn file_annotation(level: &str, path: &str, position: Option<(usize, usize)>, message: &str) -> String {
+    let mut properties = format!("file={}", escape_annotation_property(path));
+    if let Some((line, col)) = position {
+        properties.push_str(&format!(",line={},col={}", line, col));
+    }
+
+    format!("::{} {}::{}", level, properties, escape_annotation_data(message))
+}
+
+fn escape_annotation_data(data: &str) -> String {
+    data.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_annotation_property(property: &str) -> String {
+    escape_annotation_data(property).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Compiles a target together with the sources it imports, see `sources::import_closure`.
+pub fn compile_contract(sources: BTreeMap<PathBuf, Source>, settings: &CompileSettings) -> Result<CompilerOutput, Box<dyn std::error::Error>> {
+    // Create a Solc instance
+    let solc = settings.solc();
+
+    // Create the compiler input with the source code, empty when nothing was downloaded.
+    // `.yul` sources get a Yul input of their own, which drops the settings Yul doesn't take
+    let solc_settings = settings.solc_settings_for(&sources);
+    let mut inputs = CompilerInput::with_sources(sources).into_iter();
+    let first = inputs.next().ok_or("No sources to compile")?;
+
+    // Compile the source code, a mixed Solidity and Yul set is one solc run per language
+    let mut output = solc.compile_exact(&first.settings(solc_settings.clone()))?;
+    for input in inputs {
+        output.merge(solc.compile_exact(&input.settings(solc_settings.clone()))?);
+    }
+
+    Ok(output)
+}
+
+/// One of the per-contract outputs keyed by contract name, empty unless `artifact` was selected.
+pub fn emitted<T>(
+    settings: &CompileSettings,
+    artifact: Artifact,
+    output: Option<&CompilerOutput>,
+    extract: impl FnOnce(&Contracts) -> Option<Vec<(String, T)>>,
+) -> BTreeMap<String, T> {
+    if !settings.emits(artifact) {
+        return BTreeMap::new();
+    }
+    output
+        .and_then(|output| extract(&output.contracts))
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+/// Newest patch of every solc minor release, every version up to it was released too. Only used
+/// when svm's release list can't be fetched.
+const SOLC_RELEASES: [(u64, u64); 5] = [(4, 26), (5, 17), (6, 12), (7, 6), (8, 30)];
+
+/// Every solc release svm publishes plus the ones already installed, oldest first, fetched once a run.
+///
+/// Offline the published list is `SOLC_RELEASES`, so releases newer than it need installing by hand.
+pub fn solc_releases() -> Vec<Version> {
+    static RELEASES: OnceLock<Vec<Version>> = OnceLock::new();
+    RELEASES
+        .get_or_init(|| {
+            let published = svm::blocking_all_versions()
+                .map_err(|err| eprintln!("Couldn't list solc releases, falling back to the ones up to 0.8.30: {}", err))
+                .ok();
+            known_releases(published, svm::installed_versions().unwrap_or_default())
+        })
+        .clone()
+}
+
+/// `published` and `installed` releases merged, `SOLC_RELEASES` standing in for an unknown `published`.
+fn known_releases(published: Option<Vec<Version>>, installed: Vec<Version>) -> Vec<Version> {
+    let published = published.unwrap_or_else(|| {
+        SOLC_RELEASES
+            .iter()
+            .flat_map(|&(minor, newest)| (0..=newest).map(move |patch| Version::new(0, minor, patch)))
+            .collect()
+    });
+    let releases: BTreeSet<Version> = published.into_iter().chain(installed).filter(|version| version.pre.is_empty()).collect();
+    releases.into_iter().collect()
+}
+
+/// The binary of `version` when svm installed it, under `~/.svm/<version>/solc-<version>`.
+pub fn svm_solc(version: &Version) -> Option<PathBuf> {
+    let path = svm::version_path(&version.to_string()).join(format!("solc-{}", version));
+    path.is_file().then_some(path)
+}
+
+/// The release a target compiles with: the one `--locked` pins for it, or else the newest every
+/// pragma of its import closure allows.
+pub fn target_solc_version(closure: &BTreeMap<PathBuf, Source>, pinned: Option<&str>) -> Option<Version> {
+    if let Some(pinned) = pinned.and_then(|pinned| Version::parse(pinned).ok()) {
+        return Some(pinned);
+    }
+    let closure = closure.iter().map(|(path, source)| (path.clone(), source.content.to_string())).collect();
+    resolve_solc_version(&closure, &solc_releases()).ok().flatten()
+}
+
+/// Installs every one of `versions` svm doesn't have yet, in parallel on the current rayon pool,
+/// printing each as it's done. The versions that couldn't be installed come back with the error,
+/// they aren't tried again for the rest of the run.
+pub fn prewarm_solc(versions: &BTreeSet<Version>) -> BTreeMap<Version, String> {
+    static FAILED: Mutex<BTreeSet<Version>> = Mutex::new(BTreeSet::new());
+    let mut failed = FAILED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let versions: BTreeSet<Version> = versions.difference(&failed).cloned().collect();
+
+    let failures = install_missing(
+        &versions,
+        |version| svm_solc(version).is_some(),
+        |version| svm::blocking_install(version).map(drop).map_err(|err| err.to_string()),
+    );
+    failed.extend(failures.keys().cloned());
+    failures
+}
+
+/// `prewarm_solc` with what counts as installed and how to install left to the caller.
+fn install_missing(
+    versions: &BTreeSet<Version>,
+    installed: impl Fn(&Version) -> bool,
+    install: impl Fn(&Version) -> Result<(), String> + Sync,
+) -> BTreeMap<Version, String> {
+    let missing: Vec<&Version> = versions.iter().filter(|version| !installed(version)).collect();
+    if missing.is_empty() {
+        return BTreeMap::new();
+    }
+    println!("Installing solc {}", missing.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+
+    let done = AtomicUsize::new(0);
+    missing
+        .par_iter()
+        .filter_map(|version| {
+            let result = install(version);
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            match result {
+                Ok(()) => {
+                    println!("Installed solc {} ({}/{})", version, done, missing.len());
+                    None
+                }
+                Err(err) => {
+                    eprintln!("Failed to install solc {} ({}/{}): {}", version, done, missing.len(), err);
+                    Some(((*version).clone(), err))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Picks the highest of `available` that satisfies every `pragma solidity` of `sources`, several in
+/// one file included, `None` when no source has a pragma.
+///
+/// The constraints are intersected over every known solc release and `available` first, so
+/// constraints that contradict each other are told apart from a missing compiler.
+pub fn resolve_solc_version(sources: &BTreeMap<PathBuf, String>, available: &[Version]) -> Result<Option<Version>, ScraperError> {
+    let mut constraints = Vec::new();
+    for (path, content) in sources {
+        for pragma in solidity_pragmas(content) {
+            let requirement = Solc::version_req(&pragma)?;
+            constraints.push((path, pragma, requirement));
+        }
+    }
+    if constraints.is_empty() {
+        return Ok(None);
+    }
+    let satisfies = |version: &Version| constraints.iter().all(|(_, _, requirement)| requirement.matches(version));
+    // Build metadata like `+commit.7dd6d404` doesn't take part in matching
+    let release = |version: &Version| Version::new(version.major, version.minor, version.patch);
+
+    let Some(newest_release) = solc_releases().into_iter().chain(available.iter().map(release)).filter(satisfies).max() else {
+        let constraints: Vec<String> = constraints.iter().map(|(path, pragma, _)| format!("{} {}", path.display(), pragma)).collect();
+        return Err(ScraperError::NoCommonSolcVersion { constraints: constraints.join(", ") });
+    };
+
+    match available.iter().filter(|version| satisfies(&release(version))).max() {
+        Some(version) => Ok(Some(version.clone())),
+        None => Err(ScraperError::SolcVersionUnavailable {
+            newest: newest_release.to_string(),
+            found: available.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+        }),
+    }
+}
+
+/// Compiles in-memory sources in a single solc run and extracts every contract they declare.
+///
+/// Without a `solc_path` they compile with the newest release their pragmas allow, installed with
+/// svm when it's missing, see `target_solc_version`. Every `pragma solidity` has to hold for the solc
+/// they end up with, the configured one when svm can't provide it. Imports resolve against the other
+/// sources by path, Solidity and Yul sources compile in a solc run each. Contracts come in path then
+/// name order, with the outputs `settings` emits.
+pub fn compile_sources(sources: BTreeMap<PathBuf, String>, settings: CompileSettings) -> Result<Vec<ContractArtifact>, ScraperError> {
+    let kinds: BTreeMap<String, BTreeMap<String, ContractKind>> = sources
+        .iter()
+        .map(|(path, content)| (path.to_string_lossy().into_owned(), contract_kinds(content)))
+        .collect();
+    let closure: BTreeMap<PathBuf, Source> = sources.iter().map(|(path, content)| (path.clone(), Source::new(content))).collect();
+    let settings = with_svm_solc(&closure, settings, |version| {
+        prewarm_solc(&BTreeSet::from([version.clone()]));
+        svm_solc(version)
+    });
+    let version = settings.solc().version()?;
+    resolve_solc_version(&sources, std::slice::from_ref(&version))?;
+
+    let output = compile_contract(closure, &settings).map_err(|err| ScraperError::Compile(err.to_string()))?;
+    let errors: Vec<String> = output
+        .errors
+        .iter()
+        .filter(|error| error.severity == Severity::Error)
+        .map(ToString::to_string)
+        .collect();
+    if !errors.is_empty() {
+        return Err(ScraperError::Compile(errors.join("\n")));
+    }
+
+    let mut artifacts = Vec::new();
+    for (path, contracts) in &output.contracts {
+        let output = Some(&output);
+        let mut abis = emitted(&settings, Artifact::Abi, output, |contracts| get_contracts_abis(contracts, path));
+        let mut bytecodes = emitted(&settings, Artifact::Bytecode, output, |contracts| get_contracts_bytecodes(contracts, path));
+        let mut layouts = emitted(&settings, Artifact::StorageLayout, output, |contracts| get_contracts_storage_layouts(contracts, path));
+        let mut selectors = emitted(&settings, Artifact::MethodIdentifiers, output, |contracts| get_contracts_method_identifiers(contracts, path));
+        let mut gas_estimates = emitted(&settings, Artifact::GasEstimates, output, |contracts| get_contracts_gas_estimates(contracts, path));
+        let mut immutables = emitted(&settings, Artifact::ImmutableReferences, output, |contracts| get_contracts_immutable_references(contracts, path));
+        let mut unlinked = emitted(&settings, Artifact::Bytecode, output, |contracts| get_contracts_unlinked_libraries(contracts, path));
+
+        for name in contracts.keys() {
+            let mut artifact = ContractArtifact::new(name.clone(), bytecodes.remove(name));
+            artifact.kind = kinds.get(path).and_then(|kinds| kinds.get(name)).copied();
+            artifact.abi = abis.remove(name);
+            artifact.constructor_inputs = artifact.abi.as_ref().map(constructor_inputs);
+            artifact.storage_layout = layouts.remove(name);
+            artifact.gas_estimates = gas_estimates.remove(name);
+            artifact.selectors = selectors.remove(name).unwrap_or_default();
+            artifact.immutable_references = immutables.remove(name).unwrap_or_default();
+            artifact.unlinked_libraries = unlinked.remove(name).unwrap_or_default();
+            artifacts.push(artifact);
+        }
+    }
+
+    Ok(artifacts)
+}
+
+/// `settings` compiling with the `solc` binary of the release `sources` resolve to, unless they
+/// name a `solc_path` already or `solc` has none for it.
+fn with_svm_solc(sources: &BTreeMap<PathBuf, Source>, settings: CompileSettings, solc: impl Fn(&Version) -> Option<PathBuf>) -> CompileSettings {
+    if settings.solc_path.is_some() {
+        return settings;
+    }
+    match target_solc_version(sources, None).and_then(|version| solc(&version)) {
+        Some(solc_path) => CompileSettings { solc_path: Some(solc_path), ..settings },
+        None => settings,
+    }
+}
+
+/// Compiles a Vyper target with the `vyper` binary on PATH, shaped like solc's output so it's reported the same way.
+///
+/// Every fetched `.vy` file is written next to it so its imports resolve, only the ABI and bytecode are produced.
+pub fn compile_vyper(path: &str, repo: &BTreeMap<String, String>) -> Result<CompilerOutput, Box<dyn std::error::Error>> {
+    let checkout = tempfile::tempdir()?;
+    for (file, content) in repo.iter().filter(|(file, _)| file.ends_with(".vy")) {
+        let destination = checkout.path().join(file);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(destination, content)?;
+    }
+
+    let output = std::process::Command::new("vyper")
+        .args(["-f", "abi,bytecode", path])
+        .current_dir(checkout.path())
+        .output()
+        .map_err(|err| format!("Failed to run vyper: {}", err))?;
+
+    // Each requested format is printed on its own line, in order
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let output = match (output.status.success(), lines.next(), lines.next()) {
+        (true, Some(abi), Some(bytecode)) => {
+            let name = path.rsplit('/').next().unwrap_or(path).trim_end_matches(".vy");
+            let contract = serde_json::json!({
+                "abi": serde_json::from_str::<Value>(abi)?,
+                "evm": { "bytecode": { "object": bytecode.trim().trim_start_matches("0x") } },
+            });
+            serde_json::json!({ "contracts": { path: { name: contract } } })
+        }
+        _ => serde_json::json!({
+            "errors": [{
+                "type": "VyperError",
+                "component": "general",
+                "severity": "error",
+                "message": String::from_utf8_lossy(&output.stderr).trim(),
+            }],
+        }),
+    };
+
+    Ok(serde_json::from_value(output)?)
+}
+
+/// Whether solc rejected the input itself rather than the code in it, how versions without Yul
+/// support in standard JSON answer a Yul input.
+pub fn input_rejected(output: &CompilerOutput) -> bool {
+    output.errors.iter().any(|error| error.severity == Severity::Error && error.r#type == "JSONError")
+}
+
+/// Compiles the audit target `path` of `repo` from its import closure `sources`, found again when `None`.
+///
+/// With a `contest_dir` the standard JSON input is written under `standard-json/` for
+/// `emit_standard_json` and the AST streamed to `ast/` instead of kept. Only `path`'s own
+/// contracts are kept, a failure comes with the status to report it under.
+pub fn compile_target(
+    path: &str,
+    repo: &BTreeMap<String, String>,
+    sources: Option<BTreeMap<PathBuf, Source>>,
+    settings: &CompileSettings,
+    contest_dir: Option<&Path>,
+    emit_standard_json: bool,
+) -> Result<CompilerOutput, (FileStatus, String)> {
+    let failed = |err: Box<dyn std::error::Error>| (FileStatus::Failed, err.to_string());
+    let mut output = if SourceExtension::of(path).map(SourceExtension::compiler) == Some(Compiler::Vyper) {
+        compile_vyper(path, repo).map_err(failed)?
+    } else {
+        let sources = sources.unwrap_or_else(|| import_closure(path, repo));
+        if let (true, Some(contest_dir)) = (emit_standard_json, contest_dir) {
+            let input_path = contest_dir.join("standard-json").join(format!("{}.json", path));
+            if let Err(err) = write_json(&input_path, &standard_json_input(&sources, settings)) {
+                eprintln!("Error writing standard JSON input for {}: {}", path, err);
+            }
+        }
+        compile_contract(sources, settings).map_err(failed)?
+    };
+    if SourceExtension::of(path) == Some(SourceExtension::Yul) && input_rejected(&output) {
+        let reason = "this solc can't compile Yul through standard JSON";
+        println!("Skipping {}: {}", path, reason);
+        return Err((FileStatus::Skipped, reason.to_string()));
+    }
+
+    if settings.emits(Artifact::Ast) {
+        // Stream the ASTs to disk as soon as they're produced instead of holding a repo's worth
+        let ast = output.sources.remove(path).and_then(|source| source.ast);
+        if let (Some(ast), Some(contest_dir)) = (ast, contest_dir) {
+            let ast_path = contest_dir.join("ast").join(format!("{}.json", path));
+            if let Err(err) = write_json(&ast_path, &ast) {
+                eprintln!("Error writing AST for {}: {}", path, err);
+            }
+        }
+        output.sources.clear();
+    }
+    // Imported files' contracts are reported with their own targets, if at all
+    output.contracts.retain(|file, _| file == path);
+
+    Ok(output)
+}
+
+/// Why a target produced no deployable bytecode, each case sends debugging somewhere different.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoBytecode {
+    /// solc reported errors, or didn't run at all
+    CompileFailed,
+    /// Compiled fine but declares no contracts, e.g. only free functions or structs
+    NoContracts,
+    /// Every contract is an interface or abstract, which compile to empty bytecode
+    OnlyAbstract,
+    /// Every contract with bytecode needs library addresses linked in first
+    OnlyUnlinked,
+}
+
+impl std::fmt::Display for NoBytecode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NoBytecode::CompileFailed => "compilation failed",
+            NoBytecode::NoContracts => "compiled, but declares no contracts",
+            NoBytecode::OnlyAbstract => "contains only interfaces/abstract contracts",
+            NoBytecode::OnlyUnlinked => "all contracts with bytecode are unlinked, they need library addresses",
+        })
+    }
+}
+
+/// Looks at what solc actually produced for `filename`, `None` when some contract has bytecode.
+///
+/// Only meaningful when bytecode was selected, otherwise every contract looks empty.
+pub fn no_bytecode_reason(output: Option<&CompilerOutput>, filename: &str) -> Option<NoBytecode> {
+    let Some(output) = output else {
+        return Some(NoBytecode::CompileFailed);
+    };
+    let file_contracts = output.contracts.get(filename).filter(|contracts| !contracts.is_empty());
+    let Some(file_contracts) = file_contracts else {
+        let errored = output.errors.iter().any(|error| error.severity == Severity::Error);
+        return Some(if errored { NoBytecode::CompileFailed } else { NoBytecode::NoContracts });
+    };
+
+    let objects: Vec<&BytecodeObject> = file_contracts
+        .values()
+        .filter_map(|contract| contract.evm.as_ref()?.bytecode.as_ref())
+        .map(|bytecode| &bytecode.object)
+        .collect();
+    if objects.iter().any(|object| matches!(object, BytecodeObject::Bytecode(bytes) if !bytes.is_empty())) {
+        None
+    } else if objects.iter().any(|object| matches!(object, BytecodeObject::Unlinked(_))) {
+        Some(NoBytecode::OnlyUnlinked)
+    } else {
+        Some(NoBytecode::OnlyAbstract)
+    }
+}
+
+/// The standard JSON input `compile_contract` hands solc for `sources`, as accepted by verifiers.
+pub fn standard_json_input(sources: &BTreeMap<PathBuf, Source>, settings: &CompileSettings) -> StandardJsonCompilerInput {
+    let mut input = StandardJsonCompilerInput::new(
+        sources.iter().map(|(path, source)| (path.clone(), source.clone())).collect(),
+        settings.solc_settings_for(sources),
+    );
+    // The same language and sanitized settings `compile_contract` ends up with
+    if let Some(compiler_input) = CompilerInput::with_sources(sources.clone()).into_iter().next() {
+        let compiler_input = compiler_input.settings(input.settings);
+        input.language = compiler_input.language;
+        input.settings = compiler_input.settings;
+    }
+
+    input
+}
+
+/// `bytecode` without the CBOR metadata solc appends, which changes with every comment or path edit.
+///
+/// The last two bytes give the length of the CBOR map before them, bytecode that doesn't end in one
+/// is returned as is.
+pub fn strip_metadata(bytecode: &[u8]) -> &[u8] {
+    let Some(length_at) = bytecode.len().checked_sub(2) else {
+        return bytecode;
+    };
+    let length = usize::from(u16::from_be_bytes([bytecode[length_at], bytecode[length_at + 1]]));
+    match length_at.checked_sub(length) {
+        // A CBOR map header, solc's metadata is a map of a few entries
+        Some(start) if length > 0 && (0xa1..=0xb7).contains(&bytecode[start]) => &bytecode[..start],
+        _ => bytecode,
+    }
+}
+
+pub fn get_contracts_bytecodes(contracts: &Contracts, filename: &str) -> Option<Vec<(String, String)>> {
+    // Access the contracts for the specified file name
+    if let Some(file_contracts) = contracts.get(filename) {
+        // Iterate through the contracts and retrieve the names and bytecode
+        let mut bytecodes: Vec<(String, String)> = file_contracts
+            .iter()
+            .filter_map(|(contract_name, contract)| {
+                contract
+                    .evm
+                    .as_ref()
+                    .and_then(|evm| {
+                        evm.bytecode.as_ref().and_then(|bytecode| match &bytecode.object {
+                            BytecodeObject::Bytecode(bytes) => {
+                                let bytecode_str = hex::encode(bytes.as_ref());
+                                Some((contract_name.clone(), bytecode_str))
+                            }
+                            BytecodeObject::Unlinked(_) => None,
+                        })
+                    })
+            })
+            .collect();
+        // Sorted by name so re-runs produce identical output whatever map solc's output lands in
+        bytecodes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        if !bytecodes.is_empty() {
+            return Some(bytecodes);
+        }
+    }
+
+    None
+}
+
+/// The `<file>:<name>` libraries each contract's creation bytecode still has placeholders for.
+///
+/// Only contracts left unlinked are listed, `--libraries` links the ones it has an address for.
+pub fn get_contracts_unlinked_libraries(contracts: &Contracts, filename: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let unlinked: Vec<(String, Vec<String>)> = contracts
+        .get(filename)?
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            let bytecode = contract.evm.as_ref()?.bytecode.as_ref()?;
+            if !matches!(bytecode.object, BytecodeObject::Unlinked(_)) {
+                return None;
+            }
+            let libraries = bytecode
+                .link_references
+                .iter()
+                .flat_map(|(file, libraries)| libraries.keys().map(move |library| format!("{}:{}", file, library)))
+                .collect();
+            Some((contract_name.clone(), libraries))
+        })
+        .collect();
+
+    (!unlinked.is_empty()).then_some(unlinked)
+}
+
+/// The `inputs` of an ABI's `constructor` entry, each with its `name`, `type` and any `components`.
+pub fn constructor_inputs(abi: &Value) -> Vec<Value> {
+    abi.as_array()
+        .and_then(|entries| entries.iter().find(|entry| entry["type"] == "constructor"))
+        .and_then(|constructor| constructor["inputs"].as_array())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn get_contracts_abis(contracts: &Contracts, filename: &str) -> Option<Vec<(String, Value)>> {
+    // Access the contracts for the specified file name
+    let file_contracts = contracts.get(filename)?;
+
+    // The raw JSON survives newer ABI fields ethers doesn't model yet
+    let mut abis: Vec<(String, Value)> = file_contracts
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            contract
+                .abi
+                .as_ref()
+                .map(|abi| (contract_name.clone(), abi.abi_value.clone()))
+        })
+        .collect();
+    abis.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if abis.is_empty() {
+        None
+    } else {
+        Some(abis)
+    }
+}
+
+/// Creation cost plus the external and internal function costs solc estimated, per contract.
+pub fn get_contracts_gas_estimates(contracts: &Contracts, filename: &str) -> Option<Vec<(String, GasEstimates)>> {
+    // Access the contracts for the specified file name
+    let file_contracts = contracts.get(filename)?;
+
+    let mut estimates: Vec<(String, GasEstimates)> = file_contracts
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            let estimates = contract.evm.as_ref()?.gas_estimates.clone()?;
+            Some((contract_name.clone(), estimates))
+        })
+        .collect();
+    estimates.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if estimates.is_empty() {
+        None
+    } else {
+        Some(estimates)
+    }
+}
+
+pub fn get_contracts_storage_layouts(contracts: &Contracts, filename: &str) -> Option<Vec<(String, Value)>> {
+    // Access the contracts for the specified file name
+    let file_contracts = contracts.get(filename)?;
+
+    // Contracts without state variables still get an (empty) layout
+    let mut layouts: Vec<(String, Value)> = file_contracts
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            serde_json::to_value(&contract.storage_layout)
+                .ok()
+                .map(|layout| (contract_name.clone(), layout))
+        })
+        .collect();
+    layouts.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if layouts.is_empty() {
+        None
+    } else {
+        Some(layouts)
+    }
+}
+
+/// AST id of an immutable's declaration -> its offsets in the deployed bytecode.
+pub type ImmutableReferences = BTreeMap<String, Vec<Offsets>>;
+
+/// The deployed bytecode offsets of every immutable, per contract.
+pub fn get_contracts_immutable_references(contracts: &Contracts, filename: &str) -> Option<Vec<(String, ImmutableReferences)>> {
+    // Access the contracts for the specified file name
+    let file_contracts = contracts.get(filename)?;
+
+    let mut references: Vec<(String, ImmutableReferences)> = file_contracts
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            let deployed = contract.evm.as_ref()?.deployed_bytecode.as_ref()?;
+            Some((contract_name.clone(), deployed.immutable_references.clone()))
+        })
+        .collect();
+    references.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if references.is_empty() {
+        None
+    } else {
+        Some(references)
+    }
+}
+
+/// Maps each external function's 4-byte selector to its signature, per contract.
+pub fn get_contracts_method_identifiers(contracts: &Contracts, filename: &str) -> Option<Vec<(String, BTreeMap<String, String>)>> {
+    // Access the contracts for the specified file name
+    let file_contracts = contracts.get(filename)?;
+
+    // solc keys methodIdentifiers by signature, flip it so selector clashes are easy to spot
+    let mut identifiers: Vec<(String, BTreeMap<String, String>)> = file_contracts
+        .iter()
+        .filter_map(|(contract_name, contract)| {
+            contract.evm.as_ref().map(|evm| {
+                let selectors = evm
+                    .method_identifiers
+                    .iter()
+                    .map(|(signature, selector)| (selector.clone(), signature.clone()))
+                    .collect();
+                (contract_name.clone(), selectors)
+            })
+        })
+        .collect();
+    identifiers.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if identifiers.is_empty() {
+        None
+    } else {
+        Some(identifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closure(files: &[(&str, &str)]) -> BTreeMap<PathBuf, Source> {
+        files.iter().map(|(path, content)| (PathBuf::from(path), Source::new(*content))).collect()
+    }
+
+    fn sources(files: &[(&str, &str)]) -> BTreeMap<PathBuf, String> {
+        files.iter().map(|(path, content)| (PathBuf::from(path), content.to_string())).collect()
+    }
+
+    #[test]
+    fn resolves_the_newest_release_every_pragma_allows() {
+        let releases = solc_releases();
+        // A flattened file carries the pragmas of everything it inlined
+        let flattened = sources(&[("Flat.sol", "pragma solidity ^0.8.0;\ncontract A {}\npragma solidity <0.8.20;\ncontract B {}")]);
+        assert_eq!(resolve_solc_version(&flattened, &releases).unwrap(), Some(Version::new(0, 8, 19)));
+
+        // An import's pragma narrows the importer's
+        let imports = sources(&[("Vault.sol", "pragma solidity >=0.7.0;\nimport \"./Math.sol\";"), ("Math.sol", "pragma solidity ~0.7.2;")]);
+        assert_eq!(resolve_solc_version(&imports, &releases).unwrap(), Some(Version::new(0, 7, 6)));
+
+        // Only what's available counts, build metadata aside
+        let available = [Version::parse("0.7.4+commit.3f05b770").unwrap(), Version::new(0, 8, 30)];
+        assert_eq!(resolve_solc_version(&imports, &available).unwrap(), Some(available[0].clone()));
+    }
+
+    #[test]
+    fn no_pragma_resolves_to_nothing() {
+        let unpinned = sources(&[("Unpinned.sol", "contract A {}")]);
+        assert_eq!(resolve_solc_version(&unpinned, &solc_releases()).unwrap(), None);
+        assert_eq!(resolve_solc_version(&BTreeMap::new(), &solc_releases()).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_contradicting_pragmas() {
+        let contradicting = sources(&[("A.sol", "pragma solidity ^0.8.0;"), ("B.sol", "pragma solidity 0.7.6;")]);
+        let err = resolve_solc_version(&contradicting, &solc_releases()).unwrap_err();
+        assert!(matches!(err, ScraperError::NoCommonSolcVersion { .. }));
+        assert_eq!(err.to_string(), "No solc release satisfies every pragma: A.sol ^0.8.0, B.sol 0.7.6");
+
+        let unavailable = sources(&[("A.sol", "pragma solidity ^0.8.0;")]);
+        let err = resolve_solc_version(&unavailable, &[Version::new(0, 7, 6)]).unwrap_err();
+        assert_eq!(err.to_string(), "The pragmas need solc 0.8.30 or an older release satisfying all of them, found 0.7.6");
+    }
+
+    #[test]
+    fn strips_a_valid_metadata_trailer() {
+        let code = [0x60, 0x80, 0x60, 0x40];
+        // `{"solc": 0x000813}`, then its 10 byte length
+        let metadata = [0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x13, 0x00, 0x0a];
+        let bytecode = [&code[..], &metadata[..]].concat();
+        assert_eq!(strip_metadata(&bytecode), code);
+    }
+
+    #[test]
+    fn keeps_bytecode_without_a_usable_trailer() {
+        for bytecode in [
+            &[][..],
+            &[0x0a][..],
+            // Truncated: where the length points there is no map header
+            &[0x60, 0x80, 0x43, 0x00, 0x08, 0x00, 0x04][..],
+            // Longer than the bytecode itself
+            &[0x60, 0x80, 0xa1, 0xff, 0xff][..],
+            // A zero length isn't a map
+            &[0x60, 0x80, 0x00, 0x00][..],
+        ] {
+            assert_eq!(strip_metadata(bytecode), bytecode);
+        }
+    }
+
+    #[test]
+    fn knows_releases_newer_than_the_offline_table() {
+        let newer = sources(&[("src/Vault.sol", "pragma solidity ^0.8.31;\ncontract Vault {}")]);
+        let published = vec![Version::new(0, 8, 30), Version::new(0, 8, 31), Version::new(0, 8, 32)];
+        let releases = known_releases(Some(published.clone()), Vec::new());
+        assert_eq!(releases, published);
+        assert_eq!(resolve_solc_version(&newer, &releases).unwrap(), Some(Version::new(0, 8, 32)));
+
+        // Offline the table stands in, an installed newer release still counts
+        let offline = known_releases(None, vec![Version::new(0, 8, 31)]);
+        assert_eq!(offline.first(), Some(&Version::new(0, 4, 0)));
+        assert_eq!(offline.last(), Some(&Version::new(0, 8, 31)));
+        assert_eq!(resolve_solc_version(&newer, &offline).unwrap(), Some(Version::new(0, 8, 31)));
+    }
+
+    #[test]
+    fn locked_version_wins_over_the_pragma() {
+        let sources = closure(&[("src/Vault.sol", "pragma solidity ^0.8.0;\ncontract Vault {}")]);
+
+        let newest = solc_releases().into_iter().rfind(|release| release.minor == 8);
+        assert_eq!(target_solc_version(&sources, None), newest);
+        assert_eq!(target_solc_version(&sources, Some("0.8.19")), Some(Version::new(0, 8, 19)));
+        // A pin that isn't a version is ignored rather than trusted
+        assert_eq!(target_solc_version(&sources, Some("latest")), newest);
+        assert_eq!(target_solc_version(&BTreeMap::new(), None), None);
+    }
+
+    #[test]
+    fn compiles_a_non_default_pragma_with_the_svm_solc() {
+        let sources = closure(&[("src/Legacy.sol", "pragma solidity ^0.7.0;\ncontract Legacy {}")]);
+        let requested = Mutex::new(Vec::new());
+        let solc = |version: &Version| {
+            requested.lock().unwrap().push(version.clone());
+            Some(PathBuf::from(format!("/svm/{0}/solc-{0}", version)))
+        };
+
+        let settings = with_svm_solc(&sources, CompileSettings::default(), solc);
+        assert_eq!(settings.solc_path, Some(PathBuf::from("/svm/0.7.6/solc-0.7.6")));
+        assert_eq!(*requested.lock().unwrap(), [Version::new(0, 7, 6)]);
+
+        // A configured solc is kept, and so is the default one when svm has none for the pragma
+        let configured = CompileSettings { solc_path: Some(PathBuf::from("/usr/bin/solc")), ..CompileSettings::default() };
+        assert_eq!(with_svm_solc(&sources, configured, solc).solc_path, Some(PathBuf::from("/usr/bin/solc")));
+        assert_eq!(with_svm_solc(&sources, CompileSettings::default(), |_| None).solc_path, None);
+        assert_eq!(requested.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn installs_only_missing_versions_and_reports_failures() {
+        let versions: BTreeSet<Version> = [Version::new(0, 7, 6), Version::new(0, 8, 19), Version::new(0, 8, 30)].into();
+        let attempted = Mutex::new(Vec::new());
+        let failures = install_missing(
+            &versions,
+            |version| *version == Version::new(0, 8, 30),
+            |version| {
+                attempted.lock().unwrap().push(version.clone());
+                match version.minor {
+                    7 => Err("no such release".to_string()),
+                    _ => Ok(()),
+                }
+            },
+        );
+
+        let mut attempted = attempted.into_inner().unwrap();
+        attempted.sort();
+        assert_eq!(attempted, [Version::new(0, 7, 6), Version::new(0, 8, 19)]);
+        assert_eq!(failures, BTreeMap::from([(Version::new(0, 7, 6), "no such release".to_string())]));
+        assert!(install_missing(&versions, |_| true, |_| unreachable!()).is_empty());
+    }
+}
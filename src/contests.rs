@@ -0,0 +1,539 @@
+use scraper::{Html, Selector};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::Value;
+
+use chrono::{DateTime, Duration, Utc, ParseError};
+
+use regex::Regex;
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::error::ScraperError;
+use crate::github::trace_response;
+use crate::prices::UsdPrices;
+
+/// Which of a contest's `status` and `end_time` decides whether it's active when they disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Trust {
+    /// `Active` and `Upcoming` are running, any other status has ended
+    Status,
+    /// Running until `end_time`
+    Time,
+}
+
+#[allow(non_snake_case)]
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Contest {
+    pub amount: Option<String>,
+    pub audit_type: Option<String>,
+    pub award_coin: Option<String>,
+    pub codeAccess: Option<String>,
+    pub code_access: Option<String>,
+    pub contest_id: Option<u32>,
+    pub contestid: Option<u32>,
+    pub details: Option<String>,
+    pub end_time: Option<String>,
+    pub findingsRepo: Option<String>,
+    pub findings_repo: Option<String>,
+    pub formatted_amount: Option<String>,
+    pub gas_award_pool: Option<u32>,
+    pub hide: Option<bool>,
+    pub hm_award_pool: Option<u32>,
+    pub league: Option<String>,
+    pub qa_award_pool: Option<u32>,
+    pub repo: Option<String>,
+    /// Extra repos some multi-repo contests list next to `repo`
+    #[serde(default)]
+    pub repos: Option<Vec<String>>,
+    pub slug: Option<String>,
+    pub sponsor: Option<String>,
+    pub sponsor_data: SponsorData,
+    pub start_time: Option<String>,
+    pub status: Option<String>,
+    /// Categories like `DeFi` or `L2`, as listed, see `normalize_tags`
+    #[serde(default, alias = "keywords")]
+    pub tags: Option<Vec<String>>,
+    pub title: Option<String>,
+    pub total_award_pool: Option<u64>,
+    pub r#type: Option<String>,
+    pub uid: Option<String>
+}
+
+#[allow(non_snake_case)]
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct SponsorData {
+    pub created_at: Option<String>,
+    pub image: Option<String>,
+    pub imageUrl: Option<String>,
+    pub link: Option<String>,
+    pub name: Option<String>,
+    pub uid: Option<String>,
+    pub updated_at: Option<String>
+}
+
+fn github_repo_regex() -> &'static Regex {
+    static GITHUB_REPO: OnceLock<Regex> = OnceLock::new();
+    GITHUB_REPO.get_or_init(|| Regex::new(r"https://github\.com/code-423n4/[A-Za-z0-9_.-]+").unwrap())
+}
+
+impl Contest {
+    /// Whether the code is open to everyone rather than gated behind an application.
+    ///
+    /// Contests that don't say are assumed public.
+    pub fn is_public(&self) -> bool {
+        self.code_access
+            .as_deref()
+            .or(self.codeAccess.as_deref())
+            .is_none_or(|access| access.eq_ignore_ascii_case("public"))
+    }
+
+    /// Stable identity across runs and sources, the same one `ContestResult::key` gives.
+    pub fn key(&self) -> String {
+        match (self.contest_id, &self.slug) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(slug)) => slug.clone(),
+            (None, None) => self.repos().into_iter().next().unwrap_or_default(),
+        }
+    }
+
+    /// The total pool, or the sum of the per-category pools when the total isn't given, in `award_coin`.
+    pub fn award_pool(&self) -> u64 {
+        self.total_award_pool.unwrap_or_else(|| {
+            [self.hm_award_pool, self.qa_award_pool, self.gas_award_pool]
+                .into_iter()
+                .flatten()
+                .map(u64::from)
+                .sum()
+        })
+    }
+
+    /// Every repo in scope: `repo`, then `repos`, then any contest repo linked from `details`.
+    ///
+    /// Findings repos are left out, they hold reports rather than code.
+    pub fn repos(&self) -> Vec<String> {
+        let linked = self
+            .details
+            .iter()
+            .flat_map(|details| github_repo_regex().find_iter(details).map(|url| url.as_str().to_string()));
+        let candidates = self.repo.iter().cloned().chain(self.repos.iter().flatten().cloned()).chain(linked);
+
+        let findings = [&self.findings_repo, &self.findingsRepo];
+        let mut repos: Vec<String> = Vec::new();
+        for candidate in candidates {
+            let candidate = candidate.trim_end_matches('/').trim_end_matches(".git").to_string();
+            let is_findings = candidate.ends_with("-findings")
+                || findings.iter().any(|findings| findings.as_deref().is_some_and(|url| url.trim_end_matches('/') == candidate));
+            if !candidate.is_empty() && !is_findings && !repos.contains(&candidate) {
+                repos.push(candidate);
+            }
+        }
+
+        repos
+    }
+}
+
+/// Which contests to keep, every criterion that's set has to hold, e.g.
+/// `ContestFilter::new().min_award(50_000.0).league("EVM")`.
+#[derive(Debug, Clone, Default)]
+pub struct ContestFilter {
+    min_award: Option<f64>,
+    prices: Option<UsdPrices>,
+    sponsor: Option<String>,
+    league: Option<String>,
+    status: Option<String>,
+    tags: Vec<String>,
+    started_after: Option<DateTime<Utc>>,
+}
+
+impl ContestFilter {
+    /// Keeps every contest.
+    pub fn new() -> Self {
+        ContestFilter::default()
+    }
+
+    /// An award pool of at least `min_award`, in USD with `prices` and the pool's own coin otherwise.
+    pub fn min_award(mut self, min_award: f64) -> Self {
+        self.min_award = Some(min_award);
+        self
+    }
+
+    /// Compares `min_award` with the pool in USD. A pool whose coin has no price isn't known to be
+    /// too small and is kept.
+    pub fn prices(mut self, prices: UsdPrices) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    /// Run by this sponsor, case-insensitive.
+    pub fn sponsor(mut self, sponsor: impl Into<String>) -> Self {
+        self.sponsor = Some(sponsor.into());
+        self
+    }
+
+    /// Part of this league, e.g. `EVM`, case-insensitive.
+    pub fn league(mut self, league: impl Into<String>) -> Self {
+        self.league = Some(league.into());
+        self
+    }
+
+    /// With this page status, e.g. `active`, case-insensitive.
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    /// Tagged with at least one of `tags`, compared once normalized, see `normalize_tags`.
+    pub fn tags(mut self, tags: impl IntoIterator<Item = String>) -> Self {
+        self.tags = normalize_tags(tags);
+        self
+    }
+
+    /// Started after `started_after`, a contest without a start time doesn't match.
+    pub fn started_after(mut self, started_after: DateTime<Utc>) -> Self {
+        self.started_after = Some(started_after);
+        self
+    }
+
+    pub fn matches(&self, contest: &Contest) -> bool {
+        self.rejection(contest).is_none()
+    }
+
+    /// Why `contest` doesn't match, for telling the user what was skipped.
+    pub fn rejection(&self, contest: &Contest) -> Option<String> {
+        let same = |wanted: &Option<String>, actual: Option<&str>| {
+            wanted.as_deref().is_none_or(|wanted| actual.is_some_and(|actual| actual.eq_ignore_ascii_case(wanted)))
+        };
+
+        if let Some(min_award) = self.min_award {
+            let under = match &self.prices {
+                Some(prices) => prices.award_usd(contest).is_some_and(|award_usd| award_usd < min_award),
+                None => (contest.award_pool() as f64) < min_award,
+            };
+            if under {
+                return Some(format!("its award pool is under {}", min_award));
+            }
+        }
+        if !same(&self.sponsor, contest.sponsor.as_deref()) {
+            return Some(format!("it's sponsored by {}", contest.sponsor.as_deref().unwrap_or("nobody")));
+        }
+        if !same(&self.league, contest.league.as_deref()) {
+            return Some(format!("it's in league {}", contest.league.as_deref().unwrap_or("none")));
+        }
+        if !same(&self.status, contest.status.as_deref()) {
+            return Some(format!("its status is {}", contest.status.as_deref().unwrap_or("unknown")));
+        }
+        if !self.tags.is_empty() {
+            let tags = normalize_tags(contest.tags.clone().unwrap_or_default());
+            if !tags.iter().any(|tag| self.tags.contains(tag)) {
+                return Some(format!("tagged {}", if tags.is_empty() { "with nothing".to_string() } else { tags.join(", ") }));
+            }
+        }
+        if let Some(started_after) = self.started_after {
+            let start_time = contest.start_time.as_deref().and_then(|start_time| DateTime::parse_from_rfc3339(start_time).ok());
+            if start_time.is_none_or(|start_time| start_time <= started_after) {
+                return Some(format!("it didn't start after {}", started_after.to_rfc3339()));
+            }
+        }
+
+        None
+    }
+}
+
+/// The contests `filter` matches, in their original order.
+pub fn filter_contests(contests: impl IntoIterator<Item = Contest>, filter: &ContestFilter) -> Vec<Contest> {
+    contests.into_iter().filter(|contest| filter.matches(contest)).collect()
+}
+
+fn readme_tags_regex() -> &'static Regex {
+    static README_TAGS: OnceLock<Regex> = OnceLock::new();
+    // `Tags: a, b` lines, bulleted or bold, and `| Tags | a, b |` table rows
+    README_TAGS.get_or_init(|| {
+        Regex::new(r"(?im)^\s*(?:[-*|]\s*)?\**(?:tags|keywords)\**\s*(?::\**\s*(.+)|\|\s*([^|\n]+)\|)\s*$").unwrap()
+    })
+}
+
+/// The tags a contest README lists on a `Tags:` or `Keywords:` line or table row, normalized.
+pub fn readme_tags(readme: &str) -> Vec<String> {
+    normalize_tags(
+        readme_tags_regex()
+            .captures_iter(readme)
+            .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+            .flat_map(|tags| tags.as_str().split(',').map(String::from).collect::<Vec<_>>()),
+    )
+}
+
+/// Lowercased, stripped of markdown around them, deduplicated and sorted.
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    tags.into_iter()
+        .map(|tag| tag.trim_matches(|c: char| c.is_whitespace() || matches!(c, '`' | '*' | '#' | '|')).to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// The contests page scraped by `--source html`.
+pub const CONTESTS_PAGE_URL: &str = "https://code4rena.com/contests";
+
+/// The structured contests endpoint read by `--source api`.
+pub const CONTESTS_API_URL: &str = "https://code4rena.com/api/contests";
+
+/// Reads contests from the structured JSON endpoint instead of scraping the page's script tags.
+///
+/// Takes a bare array of contests, or an object with them under `contests` or `pageProps.contests`
+/// the way Next.js data routes nest them. The top level can't be an object keyed by id, that's
+/// indistinguishable from the other two.
+pub async fn get_active_contests_from_api(client: &reqwest::Client, url: &str, debug_json: bool, ended_within: Duration, trust: Trust) -> anyhow::Result<Vec<Contest>> {
+    let started = Instant::now();
+    let response = client.get(url).send().await?;
+    trace_response(url, &response, started.elapsed());
+    let response: Value = response.error_for_status()?.json().await?;
+    let entries = [&response["contests"], &response["pageProps"]["contests"]]
+        .into_iter()
+        .find_map(contest_entries)
+        .or_else(|| response.as_array().map(|entries| entries.iter().collect()))
+        .with_context(|| format!("No contests array in the response from {}", url))?;
+
+    Ok(active(deserialize_contests(&entries, debug_json), ended_within, trust))
+}
+
+/// Scrapes the contests page for contests that are still running, or ended less than `ended_within` ago.
+pub async fn get_active_contests(client: &reqwest::Client, url: &str, debug_json: bool, ended_within: Duration, trust: Trust) -> Result<Vec<Contest>, ScraperError> {
+    let started = Instant::now();
+    let response = client.get(url).send().await?;
+    trace_response(url, &response, started.elapsed());
+    let response = response.text().await?;
+
+    Ok(active(parse_contests(&response, debug_json)?, ended_within, trust))
+}
+
+/// Reads a contests JSON blob saved from an earlier scrape instead of fetching the page.
+///
+/// The blob has the same shape `parse_contests` extracts from the page's script tags.
+pub fn load_active_contests(path: &Path, debug_json: bool, ended_within: Duration, trust: Trust) -> anyhow::Result<Vec<Contest>> {
+    let blob = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let parsed_data: Value = serde_json::from_str(&blob)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let contests = contests_from_json(&parsed_data, debug_json).with_context(|| format!("No contests found in {}", path.display()))?;
+
+    Ok(active(contests, ended_within, trust))
+}
+
+fn active(contests: Vec<Contest>, ended_within: Duration, trust: Trust) -> Vec<Contest> {
+    contests
+        .into_iter()
+        .filter(|contest| is_active(contest, ended_within, trust).unwrap_or(false))
+        .collect()
+}
+
+/// Pulls every contest out of the contests page html, active or not.
+///
+/// Only string munging and JSON parsing, no I/O. Malformed input is an error, never a panic.
+pub fn parse_contests_html(html: &str) -> Result<Vec<Contest>, ScraperError> {
+    parse_contests(html, false)
+}
+
+/// `parse_contests_html`, printing every entry that fails to deserialize with `debug_json`.
+pub fn parse_contests(html: &str, debug_json: bool) -> Result<Vec<Contest>, ScraperError> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("script").unwrap();
+    let script_tags = document.select(&selector).map(|x| x.inner_html());
+    let mut cleaned_json = None;
+
+    for html in script_tags {
+        let contest_blob = html.trim_start_matches("self.__next_f.push");
+        if contest_blob.starts_with("([1,\"f:") {
+            let json_blob = contest_blob.trim_start_matches("([1,\"f:[\\\"$\\\",\\\"div\\\",null,").trim_end_matches("]\\n\"])");
+            cleaned_json = Some(json_blob.replace("\\\"", "\""));
+        }
+    }
+
+    let cleaned_json = cleaned_json.ok_or_else(|| ScraperError::ContestsPage("no script tag holds the contests".to_string()))?;
+    let parsed_data: Value = serde_json::from_str(&cleaned_json)?;
+    contests_from_json(&parsed_data, debug_json).ok_or_else(|| ScraperError::ContestsPage("no contests in the page's JSON".to_string()))
+}
+
+/// Deserializes the contests held in the page's JSON blob, `None` when it holds none.
+fn contests_from_json(parsed_data: &Value, debug_json: bool) -> Option<Vec<Contest>> {
+    contest_entries(&parsed_data["children"][3]["children"][3]["contests"]).map(|entries| deserialize_contests(&entries, debug_json))
+}
+
+/// The contests of an array, or of an object keyed by contest id, code4rena has shipped both.
+fn contest_entries(contests: &Value) -> Option<Vec<&Value>> {
+    match contests {
+        Value::Array(entries) => Some(entries.iter().collect()),
+        Value::Object(entries) => Some(entries.values().collect()),
+        _ => None,
+    }
+}
+
+/// Entries that don't deserialize into a `Contest` are dropped and counted, with `debug_json`
+/// each one is printed alongside its serde error.
+fn deserialize_contests(entries: &[&Value], debug_json: bool) -> Vec<Contest> {
+    let contests: Vec<Contest> = entries
+        .iter()
+        .filter_map(|contest| match serde_json::from_value((*contest).clone()) {
+            Ok(contest) => Some(contest),
+            Err(err) => {
+                if debug_json {
+                    eprintln!("Failed to parse contest: {}\n{:#}", err, contest);
+                }
+                None
+            }
+        })
+        .collect();
+
+    let dropped = entries.len() - contests.len();
+    if dropped > 0 {
+        eprintln!("Dropped {} of {} contest entries that failed to parse", dropped, entries.len());
+    }
+    contests
+}
+
+/// Whether the contest ends in the future, or ended no more than `ended_within` ago.
+///
+/// A `status` that disagrees with `end_time` is warned about and `trust` picks which one wins,
+/// code4rena's data is sometimes stale on either side. Without an `end_time`, as for contests
+/// announced before they're scheduled, only an `active` status counts.
+pub fn is_active(contest: &Contest, ended_within: Duration, trust: Trust) -> Result<bool, ParseError> {
+    is_active_at(contest, Utc::now(), ended_within, trust)
+}
+
+/// `is_active` as of `current_time`.
+fn is_active_at(contest: &Contest, current_time: DateTime<Utc>, ended_within: Duration, trust: Trust) -> Result<bool, ParseError> {
+    let Some(end_time) = contest.end_time.as_ref() else {
+        return Ok(contest.status.as_deref().is_some_and(|status| status.eq_ignore_ascii_case("active")));
+    };
+    let end_time = DateTime::parse_from_rfc3339(end_time)?;
+    let within = end_time > current_time - ended_within;
+
+    let Some(status) = contest.status.as_deref() else {
+        return Ok(within);
+    };
+    let running_by_status = status_is_running(status);
+    let running_by_time = end_time > current_time;
+    if running_by_status == running_by_time {
+        return Ok(within);
+    }
+
+    let trusted = match trust {
+        Trust::Status => "status",
+        Trust::Time => "end time",
+    };
+    eprintln!("Contest {} has status {} but its end time {} is {}, trusting its {}",
+        contest.key(), status, end_time, if running_by_time { "ahead" } else { "past" }, trusted);
+    Ok(match trust {
+        Trust::Status => running_by_status,
+        Trust::Time => within,
+    })
+}
+
+fn status_is_running(status: &str) -> bool {
+    status.eq_ignore_ascii_case("active") || status.eq_ignore_ascii_case("upcoming")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTESTS_PAGE: &str = include_str!("../tests/fixtures/contests.html");
+
+    #[test]
+    fn parses_contests_page() {
+        insta::assert_debug_snapshot!(parse_contests_html(CONTESTS_PAGE).unwrap());
+    }
+
+    #[test]
+    fn filters_contests() {
+        let contests: Vec<Contest> = serde_json::from_value(serde_json::json!([
+            { "slug": "small", "sponsor": "Lybra", "league": "evm", "total_award_pool": 10_000, "start_time": "2024-01-01T00:00:00Z",
+              "tags": ["DeFi"], "sponsor_data": {} },
+            { "slug": "large", "sponsor": "Lybra", "league": "evm", "total_award_pool": 90_000, "start_time": "2024-03-01T00:00:00Z",
+              "tags": ["defi", "Lending"], "sponsor_data": {} },
+            { "slug": "other", "sponsor": "Other", "league": "solana", "total_award_pool": 90_000, "sponsor_data": {} },
+        ]))
+        .unwrap();
+        let slugs = |filter: &ContestFilter| -> Vec<String> {
+            contests.iter().filter(|contest| filter.matches(contest)).filter_map(|contest| contest.slug.clone()).collect()
+        };
+
+        assert_eq!(slugs(&ContestFilter::new()), ["small", "large", "other"]);
+        assert_eq!(slugs(&ContestFilter::new().min_award(50_000.0)), ["large", "other"]);
+        assert_eq!(slugs(&ContestFilter::new().min_award(50_000.0).league("EVM")), ["large"]);
+        assert_eq!(slugs(&ContestFilter::new().sponsor("lybra").tags(["lending".to_string()])), ["large"]);
+        let march = DateTime::parse_from_rfc3339("2024-02-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(slugs(&ContestFilter::new().started_after(march)), ["large"]);
+    }
+
+    fn timed(status: Option<&str>, end_time: Option<&str>) -> Contest {
+        serde_json::from_value(serde_json::json!({ "status": status, "end_time": end_time, "sponsor_data": {} })).unwrap()
+    }
+
+    #[test]
+    fn trusts_status_or_time_when_they_disagree() {
+        let now = DateTime::parse_from_rfc3339("2024-03-10T12:00:00Z").unwrap().with_timezone(&Utc);
+        let active_at = |contest: &Contest, ended_within: Duration, trust: Trust| is_active_at(contest, now, ended_within, trust).unwrap();
+        let (ahead, past) = (Some("2024-03-11T00:00:00Z"), Some("2024-03-09T00:00:00Z"));
+
+        for trust in [Trust::Status, Trust::Time] {
+            // Agreeing status and end time leave nothing to trust
+            assert!(active_at(&timed(Some("active"), ahead), Duration::zero(), trust));
+            assert!(!active_at(&timed(Some("ended"), past), Duration::zero(), trust));
+            assert!(active_at(&timed(None, ahead), Duration::zero(), trust));
+            assert!(!active_at(&timed(None, past), Duration::zero(), trust));
+        }
+
+        let ended_early = timed(Some("ended"), ahead);
+        assert!(!active_at(&ended_early, Duration::zero(), Trust::Status));
+        assert!(active_at(&ended_early, Duration::zero(), Trust::Time));
+        let still_active = timed(Some("Active"), past);
+        assert!(active_at(&still_active, Duration::zero(), Trust::Status));
+        assert!(!active_at(&still_active, Duration::zero(), Trust::Time));
+    }
+
+    #[test]
+    fn falls_back_to_the_status_without_an_end_time() {
+        let now = DateTime::parse_from_rfc3339("2024-03-10T12:00:00Z").unwrap().with_timezone(&Utc);
+        // Without an end time there's nothing for `Trust::Time` to go by, either mode reads the status
+        for trust in [Trust::Status, Trust::Time] {
+            let active_at = |status: Option<&str>| is_active_at(&timed(status, None), now, Duration::hours(48), trust).unwrap();
+            assert!(active_at(Some("active")));
+            // An upcoming contest whose end is still to be decided isn't scraped yet
+            assert!(!active_at(Some("Upcoming")));
+            assert!(!active_at(Some("ended")));
+            assert!(!active_at(None));
+        }
+    }
+
+    #[test]
+    fn keeps_contests_that_ended_within_the_window() {
+        let now = DateTime::parse_from_rfc3339("2024-03-10T12:00:00Z").unwrap().with_timezone(&Utc);
+        let active_at = |contest: &Contest, ended_within: Duration, trust: Trust| is_active_at(contest, now, ended_within, trust).unwrap();
+        // Ended 36 hours ago
+        let ended = timed(Some("ended"), Some("2024-03-09T00:00:00Z"));
+
+        for trust in [Trust::Status, Trust::Time] {
+            assert!(!active_at(&ended, Duration::hours(24), trust));
+            assert!(active_at(&ended, Duration::hours(48), trust));
+        }
+        assert!(is_active_at(&timed(None, Some("not a date")), now, Duration::zero(), Trust::Time).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_pages() {
+        let truncated = &CONTESTS_PAGE[..CONTESTS_PAGE.len() / 2];
+        for html in ["", "<script>", "<script>self.__next_f.push([1,\"f:</script>", "<script>self.__next_f.push([1,\"f:[]\"])</script>", truncated] {
+            assert!(parse_contests_html(html).is_err(), "{:?} parsed", html);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+use crate::output::{ContestResult, ScrapeResults};
+
+/// What changed between two saved scrape results.
+#[derive(Debug, Default, Serialize)]
+pub struct ResultsDiff {
+    pub new_contests: Vec<String>,
+    pub ended_contests: Vec<String>,
+    pub changed_contests: Vec<ContestChanges>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContestChanges {
+    pub contest: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// `path:name` of every contract in the contest, mapped to its bytecode hash, empty when no bytecode was emitted.
+fn contract_hashes(contest: &ContestResult) -> BTreeMap<String, &str> {
+    contest
+        .files
+        .iter()
+        .flat_map(|file| {
+            file.contracts
+                .iter()
+                .map(move |contract| (format!("{}:{}", file.path, contract.name), contract.bytecode_hash.as_deref().unwrap_or_default()))
+        })
+        .collect()
+}
+
+fn label(contest: &ContestResult) -> String {
+    match &contest.sponsor {
+        Some(sponsor) => format!("{} ({})", contest.key(), sponsor),
+        None => contest.key(),
+    }
+}
+
+/// How two builds of a contract's creation code compare, metadata stripped.
+#[derive(Debug, Serialize)]
+pub struct BytecodeDiff {
+    pub contract: String,
+    pub from: String,
+    pub to: String,
+    pub from_length: usize,
+    pub to_length: usize,
+    /// Offsets below both lengths holding different bytes
+    pub differing_bytes: usize,
+    /// First offset the two differ at, a length change counts, `None` when they're identical
+    pub first_difference: Option<usize>,
+}
+
+impl BytecodeDiff {
+    pub fn new(contract: &str, (from, from_bytecode): (&str, &[u8]), (to, to_bytecode): (&str, &[u8])) -> Self {
+        let differing: Vec<usize> = from_bytecode
+            .iter()
+            .zip(to_bytecode)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(offset, _)| offset)
+            .collect();
+        let shorter = from_bytecode.len().min(to_bytecode.len());
+        let first_difference = differing
+            .first()
+            .copied()
+            .or_else(|| (from_bytecode.len() != to_bytecode.len()).then_some(shorter));
+
+        BytecodeDiff {
+            contract: contract.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            from_length: from_bytecode.len(),
+            to_length: to_bytecode.len(),
+            differing_bytes: differing.len(),
+            first_difference,
+        }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.first_difference.is_none()
+    }
+}
+
+impl std::fmt::Display for BytecodeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(first_difference) = self.first_difference else {
+            return writeln!(f, "{} compiles to the same bytecode at {} and {} ({} bytes, metadata stripped)",
+                self.contract, self.from, self.to, self.from_length);
+        };
+        writeln!(f, "{} bytecode differs between {} and {} (metadata stripped)", self.contract, self.from, self.to)?;
+        writeln!(f, "  length: {} -> {} bytes", self.from_length, self.to_length)?;
+        writeln!(f, "  {} of the first {} bytes differ, starting at offset {:#x}",
+            self.differing_bytes, self.from_length.min(self.to_length), first_difference)
+    }
+}
+
+pub fn diff_results(old: &ScrapeResults, new: &ScrapeResults) -> ResultsDiff {
+    let old_contests: BTreeMap<String, &ContestResult> = old.contests.iter().map(|contest| (contest.key(), contest)).collect();
+    let new_contests: BTreeMap<String, &ContestResult> = new.contests.iter().map(|contest| (contest.key(), contest)).collect();
+
+    let mut diff = ResultsDiff::default();
+    for (key, contest) in &new_contests {
+        let Some(old_contest) = old_contests.get(key) else {
+            diff.new_contests.push(label(contest));
+            continue;
+        };
+
+        let old_hashes = contract_hashes(old_contest);
+        let new_hashes = contract_hashes(contest);
+        let changes = ContestChanges {
+            contest: label(contest),
+            added: new_hashes.keys().filter(|name| !old_hashes.contains_key(*name)).cloned().collect(),
+            removed: old_hashes.keys().filter(|name| !new_hashes.contains_key(*name)).cloned().collect(),
+            changed: new_hashes
+                .iter()
+                .filter(|(name, hash)| old_hashes.get(*name).is_some_and(|old_hash| old_hash != *hash))
+                .map(|(name, _)| name.clone())
+                .collect(),
+        };
+        if !changes.added.is_empty() || !changes.removed.is_empty() || !changes.changed.is_empty() {
+            diff.changed_contests.push(changes);
+        }
+    }
+    diff.ended_contests = old_contests
+        .iter()
+        .filter(|(key, _)| !new_contests.contains_key(*key))
+        .map(|(_, contest)| label(contest))
+        .collect();
+
+    diff
+}
+
+impl std::fmt::Display for ResultsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "New contests: {}", self.new_contests.len())?;
+        for contest in &self.new_contests {
+            writeln!(f, "  + {}", contest)?;
+        }
+        writeln!(f, "Ended contests: {}", self.ended_contests.len())?;
+        for contest in &self.ended_contests {
+            writeln!(f, "  - {}", contest)?;
+        }
+        writeln!(f, "Changed contests: {}", self.changed_contests.len())?;
+        for changes in &self.changed_contests {
+            writeln!(f, "  ~ {}", changes.contest)?;
+            for contract in &changes.added {
+                writeln!(f, "      + {}", contract)?;
+            }
+            for contract in &changes.removed {
+                writeln!(f, "      - {}", contract)?;
+            }
+            for contract in &changes.changed {
+                writeln!(f, "      ~ {}", contract)?;
+            }
+        }
+
+        Ok(())
+    }
+}
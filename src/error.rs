@@ -0,0 +1,48 @@
+/// Errors surfaced by the GitHub and compile pipeline.
+#[derive(Debug, thiserror::Error)]
+pub enum ScraperError {
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("GITHUB_PA_TOKEN is not set")]
+    MissingToken,
+
+    #[error("GitHub rejected the token with {status}: {message}")]
+    InvalidToken { status: reqwest::StatusCode, message: String },
+
+    #[error("GitHub API error: {message}")]
+    GitHubApi { message: String },
+
+    #[error("Retry budget exhausted, giving up on GitHub")]
+    RetryBudgetExhausted,
+
+    #[error("Default branch of {0} not found")]
+    DefaultBranchNotFound(String),
+
+    #[error("Expected the repo as owner/name or a GitHub URL, got {0}")]
+    InvalidRepoRef(String),
+
+    #[error("Can't resolve {0} to a commit")]
+    RefNotFound(String),
+
+    #[error(transparent)]
+    Solc(#[from] ethers_solc::error::SolcError),
+
+    #[error("The pragmas need solc {newest} or an older release satisfying all of them, found {found}")]
+    SolcVersionUnavailable { newest: String, found: String },
+
+    #[error("No solc release satisfies every pragma: {constraints}")]
+    NoCommonSolcVersion { constraints: String },
+
+    #[error("Compilation failed: {0}")]
+    Compile(String),
+
+    #[error("Can't read the contests page: {0}")]
+    ContestsPage(String),
+}
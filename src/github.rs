@@ -0,0 +1,723 @@
+use base64::Engine as _;
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use tracing::{debug, error, warn};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::cache::{CachedResponse, ResponseCache};
+use crate::error::ScraperError;
+use crate::sources::{in_skipped_dir, is_source_file, SourceExtension};
+
+pub const GITHUB_API_URL: &str = "https://api.github.com/repos";
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubTreeEntry {
+    pub path: String,
+    pub r#type: String,
+    pub url: String,
+    pub sha: String,
+    /// Bytes, only given for blobs
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubTree {
+    pub tree: Vec<GitHubTreeEntry>,
+    /// Set when the repo is too big for a single recursive tree response
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Entry of a contents API directory listing.
+#[derive(Debug, Deserialize)]
+pub struct GitHubContentsEntry {
+    pub path: String,
+    pub r#type: String,
+    /// Blob API url of a file, the same kind of url the tree entries carry
+    pub git_url: Option<String>,
+    pub sha: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// A source file found in a repo listing, before it's downloaded.
+#[derive(Debug, Clone)]
+pub struct SourceEntry {
+    /// Blob API url the content is fetched from
+    pub url: String,
+    pub path: String,
+    /// Git blob SHA
+    pub sha: String,
+    pub size: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+pub struct GitHubFile {
+    pub sha: String,
+    pub node_id: String,
+    pub size: u64,
+    pub url: String,
+    pub content: String,
+    pub encoding: String
+}
+
+/// A GitHub repo, pinned to a branch, tag or commit when `reference` is given.
+#[derive(Debug, Clone)]
+pub struct RepoRef {
+    pub owner: String,
+    pub name: String,
+    pub reference: Option<String>,
+}
+
+impl RepoRef {
+    /// The repo a GitHub URL points at, see `parse_repo_ref`, `None` for any other host.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let (owner, name) = parse_repo_ref(url).ok()?;
+        Some(RepoRef { owner, name, reference: None })
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://github.com/{}/{}", self.owner, self.name)
+    }
+}
+
+/// Owner and name of a repo given as `owner/name`, `https://github.com/owner/name` (anything after
+/// the name, like `/tree/main`, is ignored) or `git@github.com:owner/name.git`.
+pub fn parse_repo_ref(repo: &str) -> Result<(String, String), ScraperError> {
+    let invalid = || ScraperError::InvalidRepoRef(repo.to_string());
+    let trimmed = repo.trim();
+    let (path, exact) = if let Some(path) = trimmed.strip_prefix("git@github.com:") {
+        (path, true)
+    } else if let Some(url) = trimmed.strip_prefix("https://").or_else(|| trimmed.strip_prefix("http://")) {
+        (url.trim_start_matches("www.").strip_prefix("github.com/").ok_or_else(invalid)?, false)
+    } else {
+        (trimmed, true)
+    };
+
+    let path = path.trim_end_matches('/');
+    let mut segments = path.split('/');
+    let (Some(owner), Some(name)) = (segments.next(), segments.next()) else {
+        return Err(invalid());
+    };
+    let name = name.trim_end_matches(".git");
+    if owner.is_empty() || name.is_empty() || (exact && segments.next().is_some()) {
+        return Err(invalid());
+    }
+
+    Ok((owner.to_string(), name.to_string()))
+}
+
+/// Builds the client shared by every request the scraper makes.
+///
+/// `HTTPS_PROXY`/`HTTP_PROXY` are picked up from the environment, an explicit `proxy` takes precedence.
+pub fn build_client(proxy: Option<&str>) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().user_agent("MyApp");
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    builder.build()
+}
+
+/// The shared HTTP client plus the state every GitHub request goes through.
+pub struct GitHubClient {
+    pub http: reqwest::Client,
+    cache: Option<ResponseCache>,
+    /// Total time all requests together may spend waiting to retry, unlimited when `None`
+    retry_budget: Option<Duration>,
+    retry_spent_ms: AtomicU64,
+    /// Time spent on requests, summed over concurrent ones
+    http_ms: AtomicU64,
+    /// Set by a rate limited response, every request waits it out so concurrent contests back off together
+    paused_until: Mutex<Option<tokio::time::Instant>>,
+    /// Least time between the starts of two requests, whichever tasks send them
+    request_delay: Option<Duration>,
+    next_slot: Mutex<Option<tokio::time::Instant>>,
+    /// Requests GitHub answered with `304 Not Modified`, served from the cache
+    not_modified: AtomicU64,
+}
+
+impl GitHubClient {
+    pub fn new(http: reqwest::Client, cache: Option<ResponseCache>) -> Self {
+        GitHubClient {
+            http,
+            cache,
+            retry_budget: None,
+            retry_spent_ms: AtomicU64::new(0),
+            http_ms: AtomicU64::new(0),
+            paused_until: Mutex::new(None),
+            request_delay: None,
+            next_slot: Mutex::new(None),
+            not_modified: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_request_delay(mut self, delay: Option<Duration>) -> Self {
+        self.request_delay = delay;
+        self
+    }
+
+    pub fn with_retry_budget(mut self, budget: Option<Duration>) -> Self {
+        self.retry_budget = budget;
+        self
+    }
+
+    /// Claims `wait` from the retry budget, `false` once it would overrun.
+    fn spend_retry(&self, wait: Duration) -> bool {
+        let Some(budget) = self.retry_budget else {
+            return true;
+        };
+        let wait_ms = wait.as_millis() as u64;
+        let spent = self.retry_spent_ms.fetch_add(wait_ms, Ordering::SeqCst) + wait_ms;
+        spent <= budget.as_millis() as u64
+    }
+
+    /// Total time spent waiting on GitHub responses, concurrent requests each counted in full.
+    pub fn http_time(&self) -> Duration {
+        Duration::from_millis(self.http_ms.load(Ordering::SeqCst))
+    }
+
+    /// Responses served from the cache after GitHub confirmed them unchanged.
+    pub fn not_modified(&self) -> u64 {
+        self.not_modified.load(Ordering::SeqCst)
+    }
+
+    fn record_request(&self, elapsed: Duration) {
+        self.http_ms.fetch_add(elapsed.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Holds back every request for `wait`, or longer if another pause already runs past it.
+    fn pause(&self, wait: Duration) {
+        let until = tokio::time::Instant::now() + wait;
+        let mut paused_until = self.paused_until.lock().unwrap_or_else(PoisonError::into_inner);
+        *paused_until = Some(paused_until.map_or(until, |paused| paused.max(until)));
+    }
+
+    async fn wait_for_pause(&self) {
+        let paused_until = *self.paused_until.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(until) = paused_until {
+            tokio::time::sleep_until(until).await;
+        }
+    }
+
+    /// Waits for this request's turn, `request_delay` after the previous one's.
+    ///
+    /// Turns are handed out in order, so concurrent requests share one rate of a request per delay.
+    async fn wait_for_slot(&self) {
+        let Some(delay) = self.request_delay else {
+            return;
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = tokio::time::Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + delay);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Whether some request was refused a retry for lack of budget.
+    pub fn retry_budget_exhausted(&self) -> bool {
+        self.retry_budget
+            .is_some_and(|budget| self.retry_spent_ms.load(Ordering::SeqCst) > budget.as_millis() as u64)
+    }
+}
+
+/// Status and body of a GitHub response.
+struct Fetched {
+    status: StatusCode,
+    body: String,
+}
+
+/// What GitHub answers with instead of the resource on errors, rate limits included.
+#[derive(Deserialize)]
+struct GitHubErrorEnvelope {
+    message: String,
+    #[allow(dead_code)]
+    documentation_url: String,
+}
+
+impl Fetched {
+    /// The body as `T`, or `ScraperError::GitHubApi` when it's GitHub's error envelope instead.
+    fn json<T: DeserializeOwned>(&self) -> Result<T, ScraperError> {
+        if let Some(err) = self.api_error() {
+            return Err(err);
+        }
+
+        Ok(serde_json::from_str(&self.body)?)
+    }
+
+    fn api_error(&self) -> Option<ScraperError> {
+        let envelope = serde_json::from_str::<GitHubErrorEnvelope>(&self.body).ok()?;
+        Some(ScraperError::GitHubApi { message: envelope.message })
+    }
+}
+
+/// Logs a response at debug level for `--trace-requests`, with the quota GitHub reports alongside it.
+pub fn trace_response(url: &str, response: &reqwest::Response, elapsed: Duration) {
+    let remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|remaining| remaining.to_str().ok())
+        .unwrap_or("-");
+    debug!(url, status = response.status().as_u16(), elapsed_ms = elapsed.as_millis() as u64, remaining, "request");
+}
+
+fn token() -> Result<String, ScraperError> {
+    std::env::var("GITHUB_PA_TOKEN").map_err(|_| ScraperError::MissingToken)
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct RateLimit {
+    resources: RateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResources {
+    core: RateLimitQuota,
+}
+
+#[derive(Deserialize)]
+pub struct RateLimitQuota {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix time the quota resets at
+    pub reset: i64,
+}
+
+/// Who the token authenticates as and how much of the hourly quota is left.
+pub struct TokenInfo {
+    pub login: String,
+    pub quota: RateLimitQuota,
+}
+
+/// Checks the token against `/user` and `/rate_limit` so a bad token fails before any scraping.
+pub async fn validate_token(client: &GitHubClient) -> Result<TokenInfo, ScraperError> {
+    let token = token()?;
+    let get = |path: &str| {
+        let url = format!("https://api.github.com/{}", path);
+        let request = client
+            .http
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token));
+        async move {
+            let started = Instant::now();
+            let response = request.send().await?;
+            client.record_request(started.elapsed());
+            trace_response(&url, &response, started.elapsed());
+            Ok::<_, reqwest::Error>(response)
+        }
+    };
+
+    // Neither goes through the cache, a stale answer would defeat the check
+    let response = get("user").await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|body| body["message"].as_str().map(String::from))
+            .unwrap_or(body);
+        return Err(ScraperError::InvalidToken { status, message });
+    }
+    let user: GitHubUser = response.json().await?;
+
+    // `/rate_limit` doesn't count against the quota it reports
+    let rate_limit: RateLimit = get("rate_limit").await?.error_for_status()?.json().await?;
+
+    Ok(TokenInfo { login: user.login, quota: rate_limit.resources.core })
+}
+
+/// Attempts per request before a rate limited response or transient error is handed back to the caller.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// What GitHub answered once rate-limit waits and transient retries are behind the request.
+struct Answer {
+    status: StatusCode,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Sends a GET to GitHub, revalidating `cached` when given, and waits out secondary rate limits,
+/// abuse detection and transient failures within the attempts and the retry budget.
+///
+/// GitHub requests carry the personal access token, everything else goes out unauthenticated.
+async fn send_github(client: &GitHubClient, url: &str, cached: Option<&CachedResponse>) -> Result<Answer, ScraperError> {
+    let mut attempt = 1;
+    loop {
+        client.wait_for_pause().await;
+        client.wait_for_slot().await;
+        let mut request = client
+            .http
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token()?));
+        if let Some(etag) = cached.and_then(|cached| cached.etag.as_ref()) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = cached.and_then(|cached| cached.last_modified.as_ref()) {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let started = Instant::now();
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                back_off_transient(client, url, attempt, err).await?;
+                attempt += 1;
+                continue;
+            }
+        };
+        client.record_request(started.elapsed());
+        trace_response(url, &response, started.elapsed());
+
+        if let Some(wait) = secondary_rate_limit_wait(&response) {
+            if attempt < MAX_ATTEMPTS {
+                if !client.spend_retry(wait) {
+                    return Err(ScraperError::RetryBudgetExhausted);
+                }
+                eprintln!("Secondary rate limit hit for {}, retrying in {}s", url, wait.as_secs());
+                warn!(url, wait_secs = wait.as_secs(), "secondary rate limit");
+                client.pause(wait);
+                attempt += 1;
+                continue;
+            }
+        }
+
+        let status = response.status();
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        let body = match response.bytes().await {
+            Ok(body) => body.to_vec(),
+            Err(err) => {
+                back_off_transient(client, url, attempt, err).await?;
+                attempt += 1;
+                continue;
+            }
+        };
+
+        // Abuse detection answers with a bare 403, only the body says what happened
+        if status == StatusCode::FORBIDDEN && is_abuse_detection(&String::from_utf8_lossy(&body)) && attempt < MAX_ATTEMPTS {
+            if !client.spend_retry(ABUSE_DETECTION_BACKOFF) {
+                return Err(ScraperError::RetryBudgetExhausted);
+            }
+            eprintln!("Abuse detection triggered by {}, retrying in {}s", url, ABUSE_DETECTION_BACKOFF.as_secs());
+            warn!(url, wait_secs = ABUSE_DETECTION_BACKOFF.as_secs(), "abuse detection");
+            client.pause(ABUSE_DETECTION_BACKOFF);
+            attempt += 1;
+            continue;
+        }
+
+        return Ok(Answer { status, etag, last_modified, body });
+    }
+}
+
+/// A GitHub API response as text, served from and stored in the cache when there is one.
+async fn github_get(client: &GitHubClient, url: &str) -> Result<Fetched, ScraperError> {
+    let cached = client.cache.as_ref().and_then(|cache| cache.get(url));
+    let Answer { status, etag, last_modified, body } = send_github(client, url, cached.as_ref()).await?;
+
+    // Unchanged since it was cached, serve the cached body
+    if status == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            client.not_modified.fetch_add(1, Ordering::SeqCst);
+            return Ok(Fetched { status: StatusCode::OK, body: cached.body });
+        }
+    }
+
+    let body = String::from_utf8_lossy(&body).into_owned();
+    let validated = etag.is_some() || last_modified.is_some();
+    if let (Some(cache), true, true) = (&client.cache, validated, status.is_success()) {
+        let response = CachedResponse { etag, last_modified, body };
+        if let Err(err) = cache.put(url, &response) {
+            eprintln!("Failed to cache {}: {}", url, err);
+        }
+        return Ok(Fetched { status, body: response.body });
+    }
+
+    Ok(Fetched { status, body })
+}
+
+/// First wait after a transient failure, doubled on every further attempt.
+const TRANSIENT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Waits before resending a request that failed with a connection reset, timeout or a body cut off
+/// mid-transfer, or hands `err` back when it isn't transient or the attempts are used up.
+async fn back_off_transient(client: &GitHubClient, url: &str, attempt: u32, err: reqwest::Error) -> Result<(), ScraperError> {
+    if !is_transient(&err) || attempt >= MAX_ATTEMPTS {
+        return Err(err.into());
+    }
+    let wait = TRANSIENT_BACKOFF * 2u32.pow(attempt - 1);
+    if !client.spend_retry(wait) {
+        return Err(ScraperError::RetryBudgetExhausted);
+    }
+    eprintln!("Transient error requesting {}, retrying in {}s: {}", url, wait.as_secs(), err);
+    warn!(url, wait_secs = wait.as_secs(), attempt, error = %err, "transient error");
+    tokio::time::sleep(wait).await;
+
+    Ok(())
+}
+
+/// Errors on the way to or from GitHub rather than ones it answered with, a 4xx won't change on a retry.
+fn is_transient(err: &reqwest::Error) -> bool {
+    !err.is_status() && (err.is_timeout() || err.is_connect() || err.is_request() || err.is_body() || err.is_decode())
+}
+
+/// Wait after an abuse detection 403, which comes without a `Retry-After`.
+const ABUSE_DETECTION_BACKOFF: Duration = Duration::from_secs(60);
+
+fn is_abuse_detection(body: &str) -> bool {
+    let message = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|body| body["message"].as_str().map(str::to_lowercase))
+        .unwrap_or_else(|| body.to_lowercase());
+    message.contains("abuse detection mechanism")
+}
+
+/// GitHub's secondary rate limits answer 403 (or 429) with a `Retry-After` in seconds
+/// instead of the `X-RateLimit-Reset` used by the primary limit.
+fn secondary_rate_limit_wait(response: &reqwest::Response) -> Option<Duration> {
+    let status = response.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// A single file of the repo at `reference` through the contents API, `None` when there's no such file.
+pub async fn get_file(client: &GitHubClient, owner: &str, repo: &str, path: &str, reference: &str) -> Result<Option<String>, ScraperError> {
+    let url = format!("{}/{}/{}/contents/{}?ref={}", GITHUB_API_URL, owner, repo, path, reference);
+    let response = github_get(client, &url).await?;
+    if response.status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let file = response.json::<GitHubFile>()?;
+    let content = base64::engine::general_purpose::STANDARD
+        .decode(file.content.replace('\n', ""))
+        .map_err(|err| ScraperError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, err)))?;
+
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
+
+/// The gzipped tarball of the repo at `reference`, in one request however many files it holds.
+///
+/// It goes through the same waits and retries as every other request, but is never cached.
+pub async fn get_tarball(client: &GitHubClient, owner: &str, repo: &str, reference: &str) -> Result<Vec<u8>, ScraperError> {
+    let url = format!("{}/{}/{}/tarball/{}", GITHUB_API_URL, owner, repo, reference);
+    let answer = send_github(client, &url, None).await?;
+    if !answer.status.is_success() {
+        let failed = Fetched { status: answer.status, body: String::from_utf8_lossy(&answer.body).into_owned() };
+        return Err(failed.api_error().unwrap_or_else(|| ScraperError::GitHubApi {
+            message: format!("{} downloading {}", answer.status, url),
+        }));
+    }
+
+    Ok(answer.body)
+}
+
+pub async fn clone_contract(client: &GitHubClient, url: &str) -> Result<GitHubFile, ScraperError> {
+    let response = github_get(client, url)
+        .await?
+        .json::<GitHubFile>()?;
+
+    Ok(response)
+}
+
+/// Every file in the repo with one of the accepted `extensions`.
+pub async fn get_contracts_urls(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    reference: &str,
+    extensions: &[SourceExtension],
+    skip_dirs: &[String],
+) -> Result<Vec<SourceEntry>, ScraperError> {
+    let api_url = format!("{}/{}/{}/git/trees/{}?recursive=1", GITHUB_API_URL, owner, repo, reference);
+
+    // Fetch the repository contents using the GitHub API
+    let response = github_get(client, &api_url)
+        .await?
+        .json::<GitHubTree>()?;
+
+    if response.truncated {
+        eprintln!("Tree of {}/{} is truncated, listing it through the contents API", owner, repo);
+        return get_contracts_urls_from_contents(client, owner, repo, reference, extensions, skip_dirs).await;
+    }
+
+    Ok(source_entries(response, extensions, skip_dirs))
+}
+
+/// The tree's files with one of `extensions` outside of `skip_dirs`.
+fn source_entries(tree: GitHubTree, extensions: &[SourceExtension], skip_dirs: &[String]) -> Vec<SourceEntry> {
+    // get the url and the path of the contract, the full path is needed to resolve imports
+    tree.tree
+        .into_iter()
+        .filter(|entry| entry.r#type == "blob" && is_source_file(&entry.path, extensions) && !in_skipped_dir(&entry.path, skip_dirs))
+        .map(|entry| SourceEntry { url: entry.url, path: entry.path, sha: entry.sha, size: entry.size })
+        .collect()
+}
+
+/// Walks the repo one directory at a time, slower than the tree API but never truncated.
+async fn get_contracts_urls_from_contents(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    reference: &str,
+    extensions: &[SourceExtension],
+    skip_dirs: &[String],
+) -> Result<Vec<SourceEntry>, ScraperError> {
+    let mut contract_urls_paths = Vec::new();
+    let mut directories = vec![String::new()];
+
+    while let Some(directory) = directories.pop() {
+        let url = format!("{}/{}/{}/contents/{}?ref={}", GITHUB_API_URL, owner, repo, directory, reference);
+        let entries = github_get(client, &url)
+            .await?
+            .json::<Vec<GitHubContentsEntry>>()?;
+
+        for entry in entries {
+            match entry.r#type.as_str() {
+                // Skipped directories aren't listed at all, that's a request less for each
+                "dir" if !in_skipped_dir(&format!("{}/", entry.path), skip_dirs) => directories.push(entry.path),
+                "file" if is_source_file(&entry.path, extensions) => {
+                    if let Some(git_url) = entry.git_url {
+                        contract_urls_paths.push(SourceEntry { url: git_url, path: entry.path, sha: entry.sha, size: entry.size });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(contract_urls_paths)
+}
+
+#[derive(Deserialize)]
+struct GitHubCommit {
+    sha: String,
+}
+
+/// The commit `reference` (a branch, tag or SHA) currently points at.
+pub async fn get_commit_sha(client: &GitHubClient, owner: &str, repo: &str, reference: &str) -> Result<String, ScraperError> {
+    // The commit list carries no diffs, unlike `commits/{ref}` which can be huge
+    let url = format!("{}/{}/{}/commits?sha={}&per_page=1", GITHUB_API_URL, owner, repo, reference);
+
+    let response = github_get(client, &url).await?;
+    // An unknown ref is a 404 or a 422, anything else failing is GitHub's to explain
+    if !response.status.is_success() && !matches!(response.status, StatusCode::NOT_FOUND | StatusCode::UNPROCESSABLE_ENTITY) {
+        if let Some(err) = response.api_error() {
+            return Err(err);
+        }
+    }
+    if response.status.is_success() {
+        if let Some(commit) = response.json::<Vec<GitHubCommit>>()?.into_iter().next() {
+            return Ok(commit.sha);
+        }
+    }
+
+    Err(ScraperError::RefNotFound(format!("{}/{}@{}", owner, repo, reference)))
+}
+
+pub async fn get_default_branch(client: &GitHubClient, owner: &str, repo: &str) -> Result<String, ScraperError> {
+    let url = format!("{}/{}/{}", GITHUB_API_URL, owner, repo);
+
+    let response = github_get(client, &url)
+        .await
+        .map_err(|err| {
+            error!("Failed to send request to GitHub API: {}", err);
+            err
+        })?;
+
+    // println!("response: {:?}", response);
+
+    if !response.status.is_success() && response.status != StatusCode::NOT_FOUND {
+        if let Some(err) = response.api_error() {
+            return Err(err);
+        }
+    }
+    if response.status.is_success() {
+        let json: serde_json::Value = response.json()?;
+        if let Some(default_branch) = json.get("default_branch") {
+            if let Some(branch_name) = default_branch.as_str() {
+                return Ok(branch_name.to_owned());
+            }
+        }
+    }
+
+    error!("Failed to retrieve default branch from GitHub API");
+    Err(ScraperError::DefaultBranchNotFound(format!("{}/{}", owner, repo)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::SKIPPED_DIRS;
+
+    #[test]
+    fn skips_build_output_directories() {
+        let paths = ["src/Foo.sol", "out/Foo.sol", "packages/core/artifacts/Foo.sol", "src/out.sol", "lib/forge-std/src/Test.sol", "node_modules/@openzeppelin/contracts/token/ERC20/ERC20.sol"];
+        let entries = paths.map(|path| serde_json::json!({ "path": path, "type": "blob", "url": "", "sha": "", "size": 1 }));
+        let tree: GitHubTree = serde_json::from_value(serde_json::json!({ "tree": entries })).unwrap();
+        let skip_dirs: Vec<String> = SKIPPED_DIRS.map(String::from).to_vec();
+
+        let paths: Vec<String> = source_entries(tree, &[SourceExtension::Sol], &skip_dirs).into_iter().map(|entry| entry.path).collect();
+        assert_eq!(paths, ["src/Foo.sol", "src/out.sol", "lib/forge-std/src/Test.sol"]);
+    }
+
+    #[test]
+    fn maps_error_envelopes() {
+        let rate_limited = Fetched {
+            status: StatusCode::FORBIDDEN,
+            body: r#"{"message": "API rate limit exceeded for user ID 1.", "documentation_url": "https://docs.github.com/rest"}"#.to_string(),
+        };
+        assert!(matches!(
+            rate_limited.json::<GitHubTree>(),
+            Err(ScraperError::GitHubApi { message }) if message == "API rate limit exceeded for user ID 1."
+        ));
+
+        let tree = Fetched { status: StatusCode::OK, body: r#"{"tree": [], "truncated": false}"#.to_string() };
+        assert!(tree.json::<GitHubTree>().is_ok());
+    }
+
+    #[test]
+    fn parses_repo_refs() {
+        let expected = ("code-423n4".to_string(), "2024-01-example".to_string());
+        for repo in [
+            "code-423n4/2024-01-example",
+            "https://github.com/code-423n4/2024-01-example",
+            "https://www.github.com/code-423n4/2024-01-example.git/",
+            "https://github.com/code-423n4/2024-01-example/tree/main/src",
+            "git@github.com:code-423n4/2024-01-example.git",
+        ] {
+            assert_eq!(parse_repo_ref(repo).unwrap(), expected, "{}", repo);
+        }
+
+        for repo in ["code-423n4", "code-423n4/", "a/b/c", "https://gitlab.com/code-423n4/2024-01-example"] {
+            assert!(parse_repo_ref(repo).is_err(), "{}", repo);
+        }
+    }
+}
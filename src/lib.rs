@@ -0,0 +1,20 @@
+//! Fetches smart contract sources, from code4rena contests or any GitHub repo, and compiles them.
+//!
+//! The `code4rena` binary drives all of it, `compile::compile_sources` is the piece to reach for with
+//! sources already in memory.
+
+pub mod awards;
+pub mod cache;
+pub mod checkout;
+pub mod compile;
+pub mod contests;
+pub mod diff;
+pub mod error;
+pub mod github;
+pub mod libraries;
+pub mod lock;
+pub mod output;
+pub mod prices;
+pub mod priority;
+pub mod sources;
+pub mod state;
@@ -0,0 +1,68 @@
+use anyhow::Context;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::checkout::blob_sha;
+use crate::sources::{RepoSources, Scope};
+
+/// Git blob SHAs of known library sources, an in-scope file with one of them is an unmodified copy.
+///
+/// Blob SHAs are what GitHub and `git ls-files -s` report, so a library's hashes come straight
+/// from a checkout of it.
+#[derive(Debug, Clone, Default)]
+pub struct KnownLibraries {
+    /// Blob SHA -> where that source comes from, e.g. `openzeppelin-contracts/contracts/token/ERC20/ERC20.sol`
+    hashes: BTreeMap<String, String>,
+}
+
+impl KnownLibraries {
+    /// Reads every `--known-libraries` path: a JSON object of blob SHA to label, or a library
+    /// checkout whose sources are hashed and labelled with their path under it.
+    pub fn load(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut known = KnownLibraries::default();
+        for path in paths {
+            if path.is_dir() {
+                let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                known.hash_directory(path, &name)?;
+            } else {
+                let hashes = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+                let hashes: BTreeMap<String, String> =
+                    serde_json::from_str(&hashes).with_context(|| format!("Failed to parse {}", path.display()))?;
+                known.hashes.extend(hashes);
+            }
+        }
+
+        Ok(known)
+    }
+
+    fn hash_directory(&mut self, directory: &Path, label: &str) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let label = format!("{}/{}", label, entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                self.hash_directory(&entry.path(), &label)?;
+            } else if label.ends_with(".sol") {
+                self.hashes.insert(blob_sha(&std::fs::read(entry.path())?), label);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the repo's own dependency sources, a target copied out of `lib/` or `node_modules/` is boilerplate too.
+    pub fn with_dependencies(mut self, sources: &RepoSources) -> Self {
+        for (path, sha) in &sources.blob_shas {
+            if Scope::of(path) == Scope::Dependency {
+                self.hashes.entry(sha.clone()).or_insert_with(|| path.clone());
+            }
+        }
+
+        self
+    }
+
+    /// The library source a blob is an unmodified copy of.
+    pub fn identify(&self, blob_sha: &str) -> Option<&str> {
+        self.hashes.get(blob_sha).map(String::as_str)
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::output::ContestResult;
+
+/// File name of the lock under `--out-dir`.
+pub const SOLC_LOCK: &str = "solc.lock";
+
+/// Bumped whenever the lockfile's shape changes incompatibly.
+pub const LOCK_VERSION: u32 = 1;
+
+/// `solc.lock`, the solc release every Solidity file was compiled with, pins the toolchain like Cargo.lock.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SolcLock {
+    pub version: u32,
+    /// Contest key -> path -> solc version, e.g. `0.8.19`
+    #[serde(default)]
+    pub contests: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for SolcLock {
+    fn default() -> Self {
+        SolcLock { version: LOCK_VERSION, contests: BTreeMap::new() }
+    }
+}
+
+impl SolcLock {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let lock = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let lock: SolcLock = toml::from_str(&lock).with_context(|| format!("Failed to parse {}", path.display()))?;
+        if lock.version != LOCK_VERSION {
+            anyhow::bail!("{} is a version {} lockfile, this build reads version {}", path.display(), lock.version, LOCK_VERSION);
+        }
+
+        Ok(lock)
+    }
+
+    /// Path -> solc version of the files of `contest` the lock pins.
+    pub fn versions(&self, contest: &str) -> BTreeMap<String, String> {
+        self.contests.get(contest).cloned().unwrap_or_default()
+    }
+
+    /// Pins every file of `results` compiled with a known solc version, files of other contests are kept.
+    pub fn record(&mut self, results: &[ContestResult]) {
+        for result in results {
+            let versions = self.contests.entry(result.key()).or_default();
+            for file in &result.files {
+                if let Some(solc_version) = &file.solc_version {
+                    versions.insert(file.path.clone(), solc_version.clone());
+                }
+            }
+        }
+        self.contests.retain(|_, versions| !versions.is_empty());
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, toml::to_string(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -1,329 +1,1269 @@
-use anyhow::Result;
-use dotenv;
+mod cli;
+mod panic;
+mod selftest;
+mod serve;
+mod shutdown;
 
-use reqwest;
-use scraper::{Html, Selector};
+use code4rena::{awards, cache, checkout, compile, contests, diff, error, github, libraries, lock, output, prices, priority, sources, state};
 
-use base64::{Engine as _, engine::general_purpose as b64};
-use serde::{Deserialize};
-use serde_json::{Value, json};
-use hex;
-
-use chrono::{DateTime, Utc, TimeZone, ParseError};
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use clap::Parser;
 
-use tracing::{error};
+use base64::{Engine as _, engine::general_purpose as b64};
+use futures::future;
+use futures::stream::{self, StreamExt};
+use rayon::prelude::*;
+use semver::Version;
+use ethers_solc::artifacts::Source;
 
-use ethers_solc::{CompilerInput, Solc, CompilerOutput};
-use ethers_solc::artifacts::{
-    Contract, Source, StandardJsonCompilerInput, Contracts, BytecodeObject
+use cli::{BytecodeFormat, Cli, Command, ContestSource, Format, GroupBy, SortBy};
+use compile::{
+    compile_contract, compile_target, get_contracts_bytecodes, file_annotation, strip_metadata, github_annotation,
+    prewarm_solc, svm_solc, target_solc_version, Artifact, CompileSettings, Diagnostics,
+};
+use contests::{
+    get_active_contests, get_active_contests_from_api, load_active_contests, Contest, CONTESTS_API_URL, CONTESTS_PAGE_URL,
 };
-use std::collections::{HashMap, BTreeMap};
+use output::{combined_abi, contests_table, contract_artifacts, discord_messages, directory_names, write_markdown_reports, write_binary, write_json, sponsor_name, write_source_archive, ContestResult, ContractArtifact, DISCORD_MESSAGE_LIMIT, FileChange, FileResult, FileStatus, JsonLines, SkipReason, SkippedFile, Manifest, ScrapeIndex, ScrapeResults};
+use awards::{get_awards, AwardSummary};
+use cache::ResponseCache;
+use serve::{SharedSnapshot, Snapshot};
+use error::ScraperError;
+use prices::UsdPrices;
+use priority::{priority_score, ContestMetrics};
+use github::{clone_contract, get_commit_sha, get_file, get_tarball, get_contracts_urls, get_default_branch, parse_repo_ref, GitHubClient, RepoRef, SourceEntry};
+use libraries::KnownLibraries;
+use lock::{SolcLock, SOLC_LOCK};
+use state::RunState;
+use sources::{contract_kinds, import_closure, is_empty_source, Compiler, SourceExtension, RepoSources, pragma_report, sloc, PragmaLock, solidity_pragma, spdx_license, Scope};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    panic::install_hook();
+    dotenv::dotenv().ok();
+    init_tracing(&cli)?;
+
+    if let Some(Command::Diff { old, new, json }) = &cli.command {
+        let diff = diff::diff_results(&ScrapeResults::read(old)?, &ScrapeResults::read(new)?);
+        if *json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            print!("{}", diff);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Selftest) = &cli.command {
+        return selftest::selftest(&cli.compile_settings());
+    }
+
+    if cli.emit.contains(&Artifact::Ast) && cli.out_dir.is_none() {
+        anyhow::bail!("--emit ast needs --out-dir to write the ASTs to");
+    }
+    if cli.sort_by == SortBy::Award && !cli.usd {
+        anyhow::bail!("--sort-by award compares USD pools, it needs --usd");
+    }
+    if cli.contracts_only && !cli.emit.contains(&Artifact::Bytecode) {
+        anyhow::bail!("--contracts-only needs bytecode in --emit to tell deployable contracts apart");
+    }
+    if cli.format == Format::Markdown && cli.out_dir.is_none() {
+        anyhow::bail!("--format markdown needs --out-dir to write the reports to");
+    }
+    if cli.bytecode_format == BytecodeFormat::Raw && cli.out_dir.is_none() {
+        anyhow::bail!("--bytecode-format raw needs --out-dir to write the .bin files to");
+    }
+
+    if let Some(solc_path) = &cli.solc_path {
+        // Fail up front rather than once per file when the binary is missing or isn't solc
+        let version = cli
+            .compile_settings()
+            .solc()
+            .version()
+            .with_context(|| format!("{} is not a usable solc binary", solc_path.display()))?;
+        println!("Using solc {} at {}", version, solc_path.display());
+    }
+
+    let http = github::build_client(cli.proxy.as_deref())?;
+    let cache = cli.cache_dir.as_deref().map(ResponseCache::open).transpose()?;
+    let client = Arc::new(GitHubClient::new(http, cache).with_retry_budget(cli.max_total_retry_time).with_request_delay(cli.request_delay()));
+    if !cli.git_clone {
+        // Every repo goes through the API, so a token without access would fail each one in turn
+        let token = github::validate_token(&client).await.context("GitHub token check failed")?;
+        let reset = Utc.timestamp_opt(token.quota.reset, 0).single().map(|reset| reset.to_rfc3339()).unwrap_or_default();
+        println!("Authenticated as {}, {} of {} requests left until {}",
+            token.login,
+            token.quota.remaining,
+            token.quota.limit,
+            reset
+        );
+    }
+    shutdown::install_handler();
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(cli.compile_threads())
+            .build()?,
+    );
+    let cli = Arc::new(cli);
+
+    let mut results = Vec::new();
+    let run = Arc::new(Mutex::new(RunContext {
+        compile_time: Duration::ZERO,
+        json_lines: match cli.format {
+            Format::Jsonl => Some(JsonLines::create(cli.output.as_deref(), cli.compress)?),
+            Format::Json | Format::Index | Format::Table | Format::GithubActions | Format::Discord | Format::Markdown => None,
+        },
+        state: cli.state_file.as_deref().map(RunState::load).transpose()?,
+        solc_lock: match (&cli.out_dir, cli.locked) {
+            (Some(out_dir), true) => Some(SolcLock::read(&out_dir.join(SOLC_LOCK))?),
+            _ => None,
+        },
+        known_libraries: KnownLibraries::load(&cli.known_libraries)?,
+    }));
+
+    if let Some(Command::BytecodeDiff { repo, from, to, contract, json }) = &cli.command {
+        let (owner, name) = parse_repo_ref(repo)?;
+        let repo = RepoRef { owner, name, reference: None };
+        let diff = bytecode_diff(&client, &cli, &repo, from, to, contract).await?;
+        if *json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            print!("{}", diff);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Repo { repo, reference, commit }) = &cli.command {
+        let (owner, name) = parse_repo_ref(repo)?;
+        let repo = RepoRef {
+            owner,
+            name,
+            reference: reference.clone().or_else(|| commit.clone()),
+        };
+        let mut repo_result = ContestResult::for_repo(&repo);
+        repo_result.directory = directory_names([(repo_result.slug.as_deref(), repo_result.key())]).pop();
+        panic::set_contest(Some(&repo_result.key()));
+        if let Err(err) = process_repo(&client, &cli, &pool, &repo, &repo.url(), &mut repo_result, &run).await {
+            eprintln!("Error processing repo {}: {:#}", repo_result.key(), err);
+            repo_result.error = Some(format!("{:#}", err));
+        }
+
+        results.push(repo_result);
+    } else if let Some(Command::Serve { listen, interval }) = &cli.command {
+        let snapshot = SharedSnapshot::default();
+        let refresh = tokio::spawn(refresh_periodically(client.clone(), cli.clone(), pool.clone(), run.clone(), snapshot.clone(), *interval));
+        serve::serve(*listen, snapshot).await?;
+        refresh.abort();
+        return Ok(());
+    } else {
+        results = scrape_contests(&client, &cli, &pool, &run).await?;
+    }
+    panic::set_contest(None);
+
+    sort_results(&cli, &mut results);
 
-#[allow(non_snake_case)]
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct Contest {
-    amount: Option<String>,
-    audit_type: Option<String>,
-    award_coin: Option<String>,
-    codeAccess: Option<String>,
-    code_access: Option<String>,
-    contest_id: Option<u32>,
-    contestid: Option<u32>,
-    details: Option<String>,
-    end_time: Option<String>,
-    findingsRepo: Option<String>,
-    findings_repo: Option<String>,
-    formatted_amount: Option<String>,
-    gas_award_pool: Option<u32>,
-    hide: Option<bool>,
-    hm_award_pool: Option<u32>,
-    league: Option<String>,
-    qa_award_pool: Option<u32>,
-    repo: Option<String>,
-    slug: Option<String>,
-    sponsor: Option<String>,
-    sponsor_data: SponsorData,
-    start_time: Option<String>,
-    status: Option<String>,
-    title: Option<String>,
-    total_award_pool: Option<u64>,
-    r#type: Option<String>,
-    uid: Option<String>
+    let failed: Vec<&ContestResult> = results.iter().filter(|result| result.error.is_some()).collect();
+    println!("Processed {} contests, {} failed", results.len(), failed.len());
+    for result in failed {
+        println!("  {}: {}", result.key(), result.error.as_deref().unwrap_or_default());
+    }
+    for result in results.iter().filter(|result| result.error.is_none()) {
+        if let (SortBy::Priority, Some(priority)) = (cli.sort_by, result.priority) {
+            print!("  [{:.3}]", priority);
+        }
+        print!("  {} ({}): {} files ({} library copies), {} in-scope contracts, {} in-scope SLOC",
+            result.key(),
+            result.sponsor.as_deref().unwrap_or_default(),
+            result.files.len(),
+            result.library_copies(),
+            result.in_scope_contracts,
+            result.sloc()
+        );
+        if !result.unsupported_repos.is_empty() {
+            print!(", skipped {} repos on unsupported hosts", result.unsupported_repos.len());
+        }
+        let skip_counts = result.skip_counts();
+        if !skip_counts.is_empty() {
+            let counts: Vec<String> = skip_counts.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect();
+            print!(", skipped files: {}", counts.join(", "));
+        }
+        println!();
+    }
+    println!("Total in-scope SLOC: {}", results.iter().map(ContestResult::sloc).sum::<usize>());
+    println!("Spent {} in GitHub requests (summed over concurrent ones) and {} compiling",
+        format_duration(client.http_time()),
+        format_duration(lock(&run).compile_time)
+    );
+    if client.not_modified() > 0 {
+        println!("{} GitHub responses were unchanged since cached and not downloaded again", client.not_modified());
+    }
+
+    if let Some(out_dir) = &cli.out_dir {
+        write_manifest(&cli, out_dir, &results)?;
+        write_solc_lock(out_dir, &results)?;
+    }
+
+    let mut invalid_bytecodes = 0;
+    if cli.validate {
+        for result in &results {
+            for file in &result.files {
+                for contract in &file.contracts {
+                    if let Some(problem) = contract.bytecode_problem() {
+                        eprintln!("Invalid bytecode for {}:{} in {}: {}", file.path, contract.name, result.key(), problem);
+                        invalid_bytecodes += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let warned_files = match cli.strict {
+        true => results.iter().flat_map(|result| &result.files).filter(|file| file.warnings > 0).count(),
+        false => 0,
+    };
+
+    if let Some(path) = &cli.combined_abi {
+        write_json(path, &combined_abi(&results))?;
+    }
+
+    match (cli.format, &cli.output) {
+        (Format::Json, Some(path)) => match cli.group_by {
+            Some(GroupBy::Sponsor) => ScrapeResults::new(results).group_by_sponsor().write(path, cli.compress)?,
+            None => ScrapeResults::new(results).write(path, cli.compress)?,
+        },
+        (Format::Index, output) => ScrapeIndex::new(&results).write(output.as_deref(), cli.compress)?,
+        (Format::Table, Some(path)) => std::fs::write(path, format!("{}\n", contests_table(&results)))?,
+        (Format::Table, None) => println!("{}", contests_table(&results)),
+        // Messages are separated by a blank line, each is pasted on its own
+        (Format::Discord, Some(path)) => std::fs::write(path, format!("{}\n", discord_messages(&results, DISCORD_MESSAGE_LIMIT).join("\n\n")))?,
+        (Format::Discord, None) => println!("{}", discord_messages(&results, DISCORD_MESSAGE_LIMIT).join("\n\n")),
+        (Format::Markdown, _) => {
+            if let Some(out_dir) = &cli.out_dir {
+                write_markdown_reports(out_dir, &results)?;
+            }
+        }
+        _ => {}
+    }
+
+    if shutdown::requested() {
+        // `exit` skips destructors, the streamed output has to be finished first
+        drop(lock(&run).json_lines.take());
+        std::process::exit(shutdown::INTERRUPTED_EXIT_CODE);
+    }
+    if invalid_bytecodes > 0 {
+        anyhow::bail!("{} contracts failed bytecode validation", invalid_bytecodes);
+    }
+    if warned_files > 0 {
+        anyhow::bail!("{} files have compiler warnings, --strict allows none", warned_files);
+    }
+
+    Ok(())
 }
 
-#[allow(non_snake_case)]
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct SponsorData {
-    created_at: Option<String>,
-    image: Option<String>,
-    imageUrl: Option<String>,
-    link: Option<String>,
-    name: Option<String>,
-    uid: Option<String>,
-    updated_at: Option<String>
+/// Compiles `contract` at both refs of `repo` and compares the two creation bytecodes.
+async fn bytecode_diff(client: &GitHubClient, cli: &Cli, repo: &RepoRef, from: &str, to: &str, contract: &str) -> Result<diff::BytecodeDiff> {
+    let mut settings = cli.compile_settings();
+    settings.emit.insert(Artifact::Bytecode);
+
+    let mut bytecodes = Vec::new();
+    for reference in [from, to] {
+        let pinned = RepoRef { reference: Some(reference.to_string()), ..repo.clone() };
+        let commit = resolve_commit(client, cli, &pinned).await?;
+        let sources = fetch_sources(client, cli, &pinned, &commit).await?;
+        let bytecode = tokio::task::block_in_place(|| compile_named_contract(&sources, contract, &settings))
+            .with_context(|| format!("Failed to compile {} at {}", contract, reference))?;
+        bytecodes.push(bytecode);
+    }
+
+    Ok(diff::BytecodeDiff::new(contract, (from, strip_metadata(&bytecodes[0])), (to, strip_metadata(&bytecodes[1]))))
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubTreeEntry {
-    path: String,
-    r#type: String,
-    url: String,
+/// Creation bytecode of the contract named `contract`, from the audit target declaring it or
+/// any other source when no target does.
+fn compile_named_contract(sources: &RepoSources, contract: &str, settings: &CompileSettings) -> Result<Vec<u8>> {
+    let declaring: Vec<&String> = sources
+        .files
+        .iter()
+        .filter(|(_, content)| contract_kinds(content).contains_key(contract))
+        .map(|(path, _)| path)
+        .collect();
+    let path = declaring
+        .iter()
+        .find(|path| Scope::of(path).is_target())
+        .or(declaring.first())
+        .with_context(|| format!("No source declares {}", contract))?;
+
+    let output = compile_contract(import_closure(path, &sources.files), settings).map_err(|err| anyhow::anyhow!("{}", err))?;
+    let errors = Diagnostics::for_file(&output.errors, path).errors;
+    if let Some(error) = errors.first() {
+        anyhow::bail!("{} failed to compile: {}", path, error.message);
+    }
+    let bytecode = get_contracts_bytecodes(&output.contracts, path)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(name, _)| name == contract)
+        .map(|(_, bytecode)| bytecode)
+        .with_context(|| format!("{} in {} has no linked bytecode", contract, path))?;
+
+    Ok(hex::decode(bytecode)?)
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubTree {
-    tree: Vec<GitHubTreeEntry>,
+/// `--trace-requests` logs for humans on stderr, `--log-file` gets the same events and more as JSON lines.
+fn init_tracing(cli: &Cli) -> Result<()> {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::{EnvFilter, Layer};
+
+    let stderr = cli.trace_requests.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_filter(EnvFilter::new("code4rena=debug"))
+    });
+    let log_file = match &cli.log_file {
+        Some(path) => {
+            let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_writer(Mutex::new(file))
+                    .with_filter(EnvFilter::new("info,code4rena=debug")),
+            )
+        }
+        None => None,
+    };
+    if stderr.is_some() || log_file.is_some() {
+        tracing_subscriber::registry().with(stderr).with(log_file).init();
+    }
+
+    Ok(())
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-struct GitHubFile {
-    sha: String,
-    node_id: String,
-    size: u64,
-    url: String,
-    content: String,
-    encoding: String
+/// Fetches the active contests and processes each of them into a result, in page order.
+async fn scrape_contests(
+    client: &Arc<GitHubClient>,
+    cli: &Arc<Cli>,
+    pool: &Arc<rayon::ThreadPool>,
+    run: &Arc<Mutex<RunContext>>,
+) -> Result<Vec<ContestResult>> {
+    let mut results = Vec::new();
+    let contests = match &cli.contests_from {
+        Some(path) => load_active_contests(path, cli.debug_json, cli.ended_within(), cli.trust)?,
+        None => fetch_contests(client, cli).await?,
+    };
+    let contests = match &cli.contest_ids_file {
+        Some(path) => select_contests(contests, path)?,
+        None => contests,
+    };
+
+    let (contests, gated): (Vec<Contest>, Vec<Contest>) = contests
+        .into_iter()
+        .partition(|contest| !cli.public_only || contest.is_public());
+    for contest in &gated {
+        eprintln!("Skipping gated contest {} ({}), pass --public-only false to try it anyway",
+            contest.key(),
+            contest.code_access.as_deref().or(contest.codeAccess.as_deref()).unwrap_or_default()
+        );
+    }
+
+    let prices = cli.usd.then(|| UsdPrices::load(cli.prices.as_deref())).transpose()?;
+    let award_usd = |contest: &Contest| prices.as_ref().and_then(|prices| prices.award_usd(contest));
+    if prices.is_some() {
+        for contest in contests.iter().filter(|contest| award_usd(contest).is_none()) {
+            eprintln!("No USD price for {} of contest {}, pass it in --prices",
+                contest.award_coin.as_deref().unwrap_or_default(),
+                contest.key()
+            );
+        }
+    }
+    let filter = cli.contest_filter(prices.clone());
+
+    // What `--tag` selects on has to be known before any contest is filtered. Otherwise READMEs are
+    // only worth their requests to the outputs that show tags, a JSON document or a Markdown report
+    let renders_tags = match cli.format {
+        Format::Json => cli.output.is_some(),
+        Format::Markdown => true,
+        Format::Jsonl | Format::Table | Format::Index | Format::GithubActions | Format::Discord => false,
+    };
+    let fetch_readmes = !cli.tag.is_empty() || renders_tags;
+    let mut contests: Vec<Contest> = stream::iter(contests)
+        .map(|mut contest| async move {
+            let readme = if fetch_readmes { readme_tags(client, &contest).await } else { Vec::new() };
+            contest.tags = Some(contest.tags.take().unwrap_or_default().into_iter().chain(readme).collect());
+            contest
+        })
+        .buffered(cli.download_concurrency())
+        .collect()
+        .await;
+    contests.retain(|contest| match filter.rejection(contest) {
+        Some(rejection) => {
+            println!("Skipping contest {}, {}", contest.key(), rejection);
+            false
+        }
+        None => true,
+    });
+
+    // Each contest is its own task, no new one starts once interrupted or out of retry budget.
+    // Results keep the page order whichever finishes first
+    let mut directories = directory_names(contests.iter().map(|contest| (contest.slug.as_deref(), contest.key())));
+    if cli.group_by == Some(GroupBy::Sponsor) {
+        for (directory, contest) in directories.iter_mut().zip(&contests) {
+            *directory = format!("{}/{}", sponsor_name(contest.sponsor.as_deref()), directory);
+        }
+    }
+    let mut running = stream::iter(contests.into_iter().zip(directories))
+        .take_while(|_| future::ready(!shutdown::requested() && !client.retry_budget_exhausted()))
+        .map(|(contest, directory)| {
+            let award_usd = award_usd(&contest);
+            tokio::spawn(run_contest(client.clone(), cli.clone(), pool.clone(), contest, award_usd, directory, run.clone()))
+        })
+        .buffered(usize::from(cli.contest_concurrency));
+    while let Some(finished) = running.next().await {
+        match finished {
+            Ok(contest_result) => results.push(contest_result),
+            Err(err) => eprintln!("Contest task failed: {}", err),
+        }
+    }
+
+    if shutdown::requested() {
+        eprintln!("Interrupted, stopping after {} contests", results.len());
+    } else if client.retry_budget_exhausted() {
+        eprintln!("Retry budget exhausted, stopping after {} contests", results.len());
+    }
+
+    Ok(results)
 }
 
-fn get_active_contests(url: &str) -> Vec<Contest> {
-    let response = reqwest::blocking::get(url).unwrap().text().unwrap();
-    let document = Html::parse_document(&response);
-    let selector = Selector::parse("script").unwrap();
-    let script_tags = document.select(&selector).map(|x| x.inner_html());
-    let mut cleaned_json = String::new();
-
-    for html in script_tags {
-        let contest_blob = html.trim_start_matches("self.__next_f.push");
-        if contest_blob.starts_with("([1,\"f:") {
-            let json_blob = contest_blob.trim_start_matches("([1,\"f:[\\\"$\\\",\\\"div\\\",null,").trim_end_matches("]\\n\"])");
-            cleaned_json = json_blob.replace("\\\"", "\"");
-        }
-    }
-
-    let data: serde_json::Result<Value, > = serde_json::from_str(&cleaned_json);
-    // println!("parsed_data: {:?}", data);
-    match data {
-        Ok(parsed_data) => {
-            let contests: Vec<Contest> = parsed_data["children"][3]["children"][3]["contests"]
-                .as_array()
-                .unwrap()
-                .iter()
-                .filter_map(|contest| serde_json::from_value(contest.clone()).ok())
-                .filter(|contest| is_active(contest).unwrap_or(false))
+/// Keeps the contests whose id or slug is listed in `path`, one per line, warning about listed ones
+/// that aren't among `contests`.
+fn select_contests(contests: Vec<Contest>, path: &Path) -> Result<Vec<Contest>> {
+    let listing = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let requested: BTreeSet<&str> = listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let mut found = BTreeSet::new();
+    let selected = contests
+        .into_iter()
+        .filter(|contest| {
+            let id = contest.contest_id.map(|id| id.to_string());
+            let matched: Vec<&str> = [id.as_deref(), contest.slug.as_deref()]
+                .into_iter()
+                .flatten()
+                .filter(|key| requested.contains(key))
                 .collect();
-            contests
+            found.extend(matched.iter().map(|key| key.to_string()));
+            !matched.is_empty()
+        })
+        .collect();
+    for missing in requested.iter().filter(|key| !found.contains(**key)) {
+        eprintln!("Contest {} from {} is not among the active contests", missing, path.display());
+    }
+
+    Ok(selected)
+}
+
+/// Rescrapes every `interval` into `snapshot` for `serve`, a failed refresh keeps the previous results.
+async fn refresh_periodically(
+    client: Arc<GitHubClient>,
+    cli: Arc<Cli>,
+    pool: Arc<rayon::ThreadPool>,
+    run: Arc<Mutex<RunContext>>,
+    snapshot: SharedSnapshot,
+    interval: Duration,
+) {
+    loop {
+        match scrape_contests(&client, &cli, &pool, &run).await {
+            Ok(mut results) => {
+                sort_results(&cli, &mut results);
+                println!("Refreshed {} contests", results.len());
+                *snapshot.write().await = Snapshot { generated_at: Some(Utc::now().to_rfc3339()), contests: results };
+            }
+            Err(err) => eprintln!("Error refreshing contests: {:#}", err),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = shutdown::wait() => break,
         }
+    }
+}
+
+fn sort_results(cli: &Cli, results: &mut [ContestResult]) {
+    // Stable, so ties keep the page order, and contests without a score or price go last
+    match cli.sort_by {
+        SortBy::Page => {}
+        SortBy::Priority => results.sort_by(|a, b| b.priority.unwrap_or(-1.0).total_cmp(&a.priority.unwrap_or(-1.0))),
+        SortBy::Award => results.sort_by(|a, b| b.award_usd.unwrap_or(-1.0).total_cmp(&a.award_usd.unwrap_or(-1.0))),
+    }
+}
+
+/// Contests from every `--url`, deduplicated by key with the first listing of a contest kept.
+///
+/// One URL failing only loses its contests, the run fails when none could be read.
+async fn fetch_contests(client: &GitHubClient, cli: &Cli) -> Result<Vec<Contest>> {
+    let default_url = match cli.source {
+        ContestSource::Html => CONTESTS_PAGE_URL,
+        ContestSource::Api => CONTESTS_API_URL,
+    };
+    let urls: Vec<&str> = match cli.url.is_empty() {
+        true => vec![default_url],
+        false => cli.url.iter().map(String::as_str).collect(),
+    };
+
+    let mut contests = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut failed = 0;
+    for url in &urls {
+        let fetched = match cli.source {
+            ContestSource::Html => get_active_contests(&client.http, url, cli.debug_json, cli.ended_within(), cli.trust).await.map_err(anyhow::Error::from),
+            ContestSource::Api => match get_active_contests_from_api(&client.http, url, cli.debug_json, cli.ended_within(), cli.trust).await {
+                // The html page is only a stand-in for the default endpoint, a given URL is taken as is
+                Err(err) if cli.url.is_empty() => {
+                    eprintln!("Error reading contests from {}, falling back to the html page: {:#}", url, err);
+                    get_active_contests(&client.http, CONTESTS_PAGE_URL, cli.debug_json, cli.ended_within(), cli.trust).await.map_err(anyhow::Error::from)
+                }
+                fetched => fetched,
+            },
+        };
+
+        match fetched {
+            Ok(fetched) => contests.extend(fetched.into_iter().filter(|contest| seen.insert(contest.key()))),
+            Err(err) if urls.len() > 1 => {
+                eprintln!("Error reading contests from {}: {:#}", url, err);
+                failed += 1;
+            }
+            Err(err) => return Err(err.context(format!("Failed to read contests from {}", url))),
+        }
+    }
+    if failed == urls.len() {
+        anyhow::bail!("No contests could be read from any --url");
+    }
+
+    Ok(contests)
+}
+
+/// `humantime` rendering at millisecond precision, `1m 3s 250ms` rather than nanoseconds.
+fn format_duration(duration: Duration) -> humantime::FormattedDuration {
+    humantime::format_duration(Duration::from_millis(duration.as_millis() as u64))
+}
+
+/// Where a run's results go as they're produced, and what it remembers for the next run.
+struct RunContext {
+    /// Wall time spent compiling, across every repo
+    compile_time: Duration,
+    json_lines: Option<JsonLines>,
+    state: Option<RunState>,
+    /// The lock `--locked` compiles against
+    solc_lock: Option<SolcLock>,
+    known_libraries: KnownLibraries,
+}
+
+/// The run context is shared by every contest task.
+fn lock(run: &Mutex<RunContext>) -> MutexGuard<'_, RunContext> {
+    run.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Processes one contest as a task of its own, a failure is recorded in the result it returns.
+async fn run_contest(
+    client: Arc<GitHubClient>,
+    cli: Arc<Cli>,
+    pool: Arc<rayon::ThreadPool>,
+    contest: Contest,
+    award_usd: Option<f64>,
+    directory: String,
+    run: Arc<Mutex<RunContext>>,
+) -> ContestResult {
+    println!("id: {} status: {} sponsor: {}",
+        contest.contest_id.unwrap_or_default(),
+        contest.status.as_deref().unwrap_or_default(),
+        contest.sponsor.as_deref().unwrap_or_default()
+    );
+    let mut contest_result = ContestResult::new(&contest);
+    contest_result.directory = Some(directory);
+    contest_result.award_usd = award_usd;
+    // With several contests in flight the contest of a panicking file can't be told apart
+    if cli.contest_concurrency == 1 {
+        panic::set_contest(Some(&contest_result.key()));
+    }
+
+    // One bad contest is recorded and skipped, the rest still get processed
+    if let Err(err) = process_contest(&client, &cli, &pool, &contest, &mut contest_result, &run).await {
+        eprintln!("Error processing contest {}: {:#}", contest_result.key(), err);
+        contest_result.error = Some(format!("{:#}", err));
+    }
+    if cli.fetch_awards {
+        contest_result.awards = fetch_awards(&client, &contest).await;
+    }
+    tracing::info!(
+        contest = contest_result.key(),
+        files = contest_result.files.len(),
+        skipped = contest_result.skipped.len(),
+        contracts = contest_result.in_scope_contracts,
+        error = contest_result.error.as_deref(),
+        "contest processed"
+    );
+    let metrics = ContestMetrics { sloc: contest_result.sloc(), award_usd };
+    contest_result.priority = Some(priority_score(&contest, &metrics, &cli.priority_weights()));
+
+    contest_result
+}
+
+/// The tags the README of the contest's first repo lists, none when it has no README or it can't be read.
+async fn readme_tags(client: &GitHubClient, contest: &Contest) -> Vec<String> {
+    let Some(repo) = contest.repos().first().and_then(|url| RepoRef::from_url(url)) else {
+        return Vec::new();
+    };
+    match get_file(client, &repo.owner, &repo.name, "README.md", repo.reference.as_deref().unwrap_or("HEAD")).await {
+        Ok(readme) => readme.as_deref().map(contests::readme_tags).unwrap_or_default(),
         Err(err) => {
-            eprintln!("Error parsing JSON: {}", err);
+            eprintln!("Error fetching the README of {}: {}", repo.url(), err);
             Vec::new()
         }
     }
 }
 
-fn is_active(contest: &Contest) -> Result<bool, ParseError> {
-    let current_time = Utc::now();
-    let end_time = contest.end_time.as_ref().unwrap();
-    let end_time = DateTime::parse_from_rfc3339(&end_time)?;
-    
-    Ok(end_time > current_time)
+/// The payouts of `contest` once it has ended, a missing or unreadable leaderboard only loses the summary.
+async fn fetch_awards(client: &GitHubClient, contest: &Contest) -> Option<AwardSummary> {
+    let ended = contest
+        .end_time
+        .as_deref()
+        .and_then(|end_time| chrono::DateTime::parse_from_rfc3339(end_time).ok())
+        .is_some_and(|end_time| end_time < Utc::now());
+    let slug = contest.slug.as_deref().filter(|_| ended)?;
+
+    match get_awards(&client.http, slug).await {
+        Ok(Some(awards)) => {
+            println!("{} awarded ${:.0} to {} wardens", contest.key(), awards.total_awarded, awards.payouts.len());
+            Some(awards)
+        }
+        Ok(None) => {
+            println!("{} has no published awards yet", contest.key());
+            None
+        }
+        Err(err) => {
+            eprintln!("Error fetching the awards of {}: {}", contest.key(), err);
+            None
+        }
+    }
 }
 
-fn clone_contract(url: &str) -> Result<GitHubFile, reqwest::Error> {
-    dotenv::dotenv().ok();
-    
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(url)
-        .header("User-Agent", "MyApp")
-        .header("Authorization", format!("Bearer {}", std::env::var("GITHUB_PA_TOKEN").unwrap()))
-        .send()?
-        .json::<GitHubFile>()?;
-        // .send()?
-        // .text()?;
-    
-    Ok(response)
+/// Fetches and compiles every repo of a single contest.
+async fn process_contest(
+    client: &GitHubClient,
+    cli: &Cli,
+    pool: &rayon::ThreadPool,
+    contest: &Contest,
+    result: &mut ContestResult,
+    run: &Mutex<RunContext>,
+) -> Result<()> {
+    if cli.format == Format::Discord {
+        // The contest listing is all a Discord summary shows
+        return Ok(());
+    }
+    let repo_urls = contest.repos();
+    if repo_urls.is_empty() {
+        anyhow::bail!("Contest has no repo");
+    }
+
+    if cli.skip_existing {
+        // The heads resolved here are the ones the repos get processed at if the contest isn't skipped
+        for repo_url in &repo_urls {
+            if let Some(repo) = RepoRef::from_url(repo_url) {
+                let commit = resolve_commit(client, cli, &repo).await.with_context(|| format!("Failed to resolve {}", repo_url))?;
+                result.commits.insert(repo_url.clone(), commit);
+            }
+        }
+        if lock(run).state.as_ref().is_some_and(|state| state.contest_done(&result.key(), &result.commits)) {
+            println!("Contest {} was already processed at the same commits, skipping", result.key());
+            return Ok(());
+        }
+    }
+
+    for repo_url in &repo_urls {
+        if shutdown::requested() {
+            anyhow::bail!("Interrupted before {}", repo_url);
+        }
+        // Cross-posted contests sometimes point at another host, there's no API to fetch those through
+        let Some(repo) = RepoRef::from_url(repo_url) else {
+            eprintln!("Skipping {}: unsupported host, only GitHub repos can be fetched", repo_url);
+            result.unsupported_repos.push(repo_url.clone());
+            continue;
+        };
+        process_repo(client, cli, pool, &repo, repo_url, result, run)
+            .await
+            .with_context(|| format!("Failed to process {}", repo_url))?;
+    }
+
+    // An index compiles nothing, so it doesn't count as having processed the contest
+    if let (Some(state), false) = (&mut lock(run).state, cli.format == Format::Index) {
+        state.complete_contest(&result.key(), &result.commits);
+        state.save()?;
+    }
+
+    Ok(())
 }
 
-fn get_contracts_urls(api_url: &str) -> Result<Vec<(String, String)>, reqwest::Error> {
-    dotenv::dotenv().ok();
-    // Fetch the repository contents using the GitHub API
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(api_url)
-        .header("User-Agent", "MyApp")
-        .header("Authorization", format!("Bearer {}", std::env::var("GITHUB_PA_TOKEN").unwrap()))
-        .send()?
-        .json::<GitHubTree>()?;
-
-    // get the url and the filename/path of the contract
-    let contract_urls_paths: Vec<(String, String)> = response
-        .tree
-        .into_iter()
-        .filter(|entry| entry.r#type == "blob" && entry.path.ends_with(".sol"))
-        .map(|entry| {
-            let path = Path::new(&entry.path);
-            let filename = path
-                .file_name()
-                .and_then(|filename| filename.to_str())
-                .unwrap_or(&entry.path);
-
-            // (entry.url, entry.path)  // return path
-            (entry.url, filename.to_string()) // return filename
+/// Fetches and compiles a repo into `result`, tagging its files with `repo_url`.
+///
+/// With `--only-changed`, a repo still at the commit recorded in the state file is skipped.
+async fn process_repo(
+    client: &GitHubClient,
+    cli: &Cli,
+    pool: &rayon::ThreadPool,
+    repo: &RepoRef,
+    repo_url: &str,
+    result: &mut ContestResult,
+    run: &Mutex<RunContext>,
+) -> Result<()> {
+    let commit = match result.commits.get(repo_url) {
+        Some(commit) => commit.clone(),
+        None => resolve_commit(client, cli, repo).await?,
+    };
+    result.commits.insert(repo_url.to_string(), commit.clone());
+    let last_seen = lock(run).state.as_ref().and_then(|state| state.commits.get(repo_url).cloned());
+    if cli.only_changed && last_seen.as_ref() == Some(&commit) {
+        println!("{} is unchanged since the last run at {}, skipping", repo_url, commit);
+        return Ok(());
+    }
+
+    if cli.format == Format::Index {
+        // Listing is all an index needs, and nothing compiled means nothing to record in the state
+        result.files.extend(index_repo(client, cli, repo, repo_url, &commit).await?);
+        return Ok(());
+    }
+
+    let sources = fetch_sources(client, cli, repo, &commit).await?;
+    let contest_dir = cli.out_dir.as_ref().map(|out_dir| result.artifact_dir(out_dir, Some(repo_url)));
+
+    if let (true, Some(contest_dir)) = (cli.save_sources, &contest_dir) {
+        write_source_archive(&contest_dir.join("sources.tar.zst"), &sources.files)?;
+    }
+    let (known_libraries, completed, locked) = {
+        let run = lock(run);
+        let completed = run.state.as_ref().map(|state| state.completed_files(&result.key())).unwrap_or_default();
+        let locked = run.solc_lock.as_ref().map(|solc_lock| solc_lock.versions(&result.key()));
+        (run.known_libraries.clone().with_dependencies(&sources), completed, locked)
+    };
+    let job = RepoJob {
+        sources: &sources,
+        known_libraries: &known_libraries,
+        repo_url,
+        contest_dir: contest_dir.as_deref(),
+        completed,
+        locked,
+    };
+    let started = Instant::now();
+    // Compiling blocks, other contests' tasks move to another worker meanwhile
+    let (files, skipped) = tokio::task::block_in_place(|| {
+        compile_repo(cli, pool, &job, |batch| {
+            let mut run = lock(run);
+            if let Some(json_lines) = run.json_lines.as_mut() {
+                json_lines.write_files(result, batch)?;
+            }
+            // Only once its output is written does a file count as done
+            if let Some(state) = run.state.as_mut() {
+                state.complete_files(&result.key(), batch);
+                state.save()?;
+            }
+            Ok(())
         })
-        .collect();
+    })?;
+    lock(run).compile_time += started.elapsed();
+    result.files.extend(files);
+    result.skipped.extend(skipped);
+    result.in_scope_contracts = result.count_contracts();
+    // A cut short repo keeps what it compiled but isn't recorded as done
+    if shutdown::requested() {
+        anyhow::bail!("Interrupted");
+    }
+
+    if let Some(state) = &mut lock(run).state {
+        state.commits.insert(repo_url.to_string(), commit);
+        state.save()?;
+    }
 
-    Ok(contract_urls_paths)
+    Ok(())
 }
 
+/// The commit the repo's reference, or its default branch, points at right now.
+///
+/// Everything after this is fetched at that commit, the branch can move while the files are downloading.
+async fn resolve_commit(client: &GitHubClient, cli: &Cli, repo: &RepoRef) -> Result<String> {
+    if cli.git_clone {
+        return checkout::resolve_commit(repo).await;
+    }
+
+    let reference = match &repo.reference {
+        Some(reference) => reference.clone(),
+        None => {
+            let default_branch = get_default_branch(client, &repo.owner, &repo.name).await?;
+            println!("Default branch: {}", default_branch);
+            default_branch
+        }
+    };
+    let commit = get_commit_sha(client, &repo.owner, &repo.name, &reference).await?;
+    println!("Resolved {} to {}", reference, commit);
 
-fn get_default_branch(owner: &str, repo: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let github_api_url = "https://api.github.com/repos";
-    let url = format!("{}/{}/{}", github_api_url, owner, repo);
+    Ok(commit)
+}
 
-    dotenv::dotenv().ok();
+/// Every `--ext` source in the repo keyed by path, either cloned with git, unpacked from a tarball or
+/// downloaded file by file.
+async fn fetch_sources(client: &GitHubClient, cli: &Cli, repo: &RepoRef, commit: &str) -> Result<RepoSources> {
+    if cli.git_clone {
+        let pinned = RepoRef { reference: Some(commit.to_string()), ..repo.clone() };
+        return checkout::clone_sources(&pinned, &cli.ext, &cli.skip_dir).await;
+    }
+    if cli.tarball {
+        println!("Downloading the tarball of {} at {}", repo.url(), commit);
+        let tarball = get_tarball(client, &repo.owner, &repo.name, commit).await?;
+        let (extensions, skip_dirs) = (cli.ext.clone(), cli.skip_dir.clone());
+        let mut sources = tokio::task::spawn_blocking(move || checkout::extract_tarball(&tarball, &extensions, &skip_dirs)).await??;
+        sources.commit = Some(commit.to_string());
+        return Ok(sources);
+    }
 
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "MyApp")
-        .header("Authorization", format!("Bearer {}", std::env::var("GITHUB_PA_TOKEN").unwrap()))
-        .send()
-        .map_err(|err| {
-            error!("Failed to send request to GitHub API: {}", err);
+    let contract_data = get_contracts_urls(client, &repo.owner, &repo.name, commit, &cli.ext, &cli.skip_dir).await.map_err(|err| {
+        eprintln!("Error fetching GitHub repository contents: {}", err);
+        err
+    })?;
+
+    // Download every contract up front, bounded by the download concurrency
+    let downloaded: Vec<_> = stream::iter(contract_data)
+        .map(|SourceEntry { url, path, .. }| async move {
+            println!("// Solidity contract URL: {}", url);
+            println!("// Solidity contract path: {}", path);
+            match clone_contract(client, &url).await {
+                Ok(contract) => {
+                    let contract_content = contract.content.replace('\n', "");
+                    match b64::STANDARD.decode(contract_content) {
+                        Ok(contract_decoded_content) => {
+                            let contract_decoded_string = String::from_utf8_lossy(&contract_decoded_content).into_owned();
+                            Ok((path, url, contract.sha, contract_decoded_string))
+                        }
+                        Err(err) => {
+                            eprintln!("Error decoding {}: {}", url, err);
+                            Err((path, format!("Error decoding: {}", err)))
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Error downloading {}: {}", url, err);
+                    Err((path, format!("Error downloading: {}", err)))
+                }
+            }
         })
-        .unwrap();
-    
-    // println!("response: {:?}", response);
-
-    if response.status().is_success() {
-        let json: serde_json::Value = response.json()?;
-        if let Some(default_branch) = json.get("default_branch") {
-            if let Some(branch_name) = default_branch.as_str() {
-                return Ok(branch_name.to_owned());
+        .buffer_unordered(cli.download_concurrency())
+        .collect()
+        .await;
+    // Downloads that ran out of budget were skipped one by one, the repo is incomplete
+    if client.retry_budget_exhausted() {
+        return Err(ScraperError::RetryBudgetExhausted.into());
+    }
+
+    let mut sources = RepoSources { commit: Some(commit.to_string()), ..RepoSources::default() };
+    for download in downloaded {
+        match download {
+            Ok((path, url, blob_sha, content)) => {
+                sources.blob_shas.insert(path.clone(), blob_sha);
+                sources.urls.insert(path.clone(), url);
+                sources.files.insert(path, content);
+            }
+            Err((path, err)) => {
+                sources.failed.insert(path, err);
             }
         }
     }
+    sources.foundry_toml = match get_file(client, &repo.owner, &repo.name, "foundry.toml", commit).await {
+        Ok(foundry_toml) => foundry_toml,
+        Err(err) => {
+            eprintln!("Error fetching foundry.toml of {}: {}", repo.url(), err);
+            None
+        }
+    };
 
-    error!("Failed to retrieve default branch from GitHub API");
-    Err("Default branch not found".into())
+    Ok(sources)
 }
 
-// fn compile_contract(filename: &str, source_code: &str) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-fn compile_contract(filename: &str, source_code: &str) -> Result<Contracts, Box<dyn std::error::Error>> {
-    // Create a Solc instance
-    let solc = Solc::default();
+/// One repo's worth of work for `compile_repo`.
+struct RepoJob<'a> {
+    sources: &'a RepoSources,
+    known_libraries: &'a KnownLibraries,
+    repo_url: &'a str,
+    /// Where artifacts go, nothing is written without one
+    contest_dir: Option<&'a Path>,
+    /// Path -> blob SHA of the targets an earlier run already produced output for
+    completed: BTreeMap<String, String>,
+    /// Path -> solc version `--locked` requires
+    locked: Option<BTreeMap<String, String>>,
+}
 
-    // Create the compiler input with the Solidity source code
-    let mut sources = BTreeMap::new();
-    let source = Source::new(source_code);
-    sources.insert(PathBuf::from(filename.to_string()), source);
+/// The in-scope files of a repo with their sizes, listed without downloading unless the repo is cloned.
+async fn index_repo(client: &GitHubClient, cli: &Cli, repo: &RepoRef, repo_url: &str, commit: &str) -> Result<Vec<FileResult>> {
+    let entries: Vec<(String, Option<String>, u64)> = if cli.git_clone || cli.tarball {
+        let sources = fetch_sources(client, cli, repo, commit).await?;
+        sources
+            .files
+            .iter()
+            .map(|(path, content)| (path.clone(), sources.blob_shas.get(path).cloned(), content.len() as u64))
+            .collect()
+    } else {
+        get_contracts_urls(client, &repo.owner, &repo.name, commit, &cli.ext, &cli.skip_dir)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.path, Some(entry.sha), entry.size))
+            .collect()
+    };
 
-    // Create the compiler input with the Solidity source code
-    let input = CompilerInput::with_sources(sources);
+    Ok(entries
+        .into_iter()
+        .filter(|(path, _, _)| Scope::of(path).is_target())
+        .map(|(path, blob_sha, size)| FileResult::indexed(path, repo_url, blob_sha, size))
+        .collect())
+}
 
-    // Compile the Solidity source code
-    let output = solc.compile_exact(&input[0]).unwrap();
+/// Compiles and reports the audit targets of `job`, skipping the ones already completed at the same blob SHA.
+///
+/// `on_batch` sees the files of every `--batch-size` batch as soon as it's done.
+fn compile_repo(
+    cli: &Cli,
+    pool: &rayon::ThreadPool,
+    job: &RepoJob<'_>,
+    mut on_batch: impl FnMut(&[FileResult]) -> Result<()>,
+) -> Result<(Vec<FileResult>, Vec<SkippedFile>)> {
+    let RepoJob { sources, known_libraries, repo_url, contest_dir, ref completed, ref locked } = *job;
+    let mut settings = cli.compile_settings();
+    if !settings.via_ir && sources.foundry_via_ir() {
+        println!("{} sets via_ir in foundry.toml, compiling through the IR pipeline", repo_url);
+        settings.via_ir = true;
+    }
+    let repo = &sources.files;
+    let skip = |path: &str, reason: SkipReason, message: Option<String>| SkippedFile {
+        path: path.to_string(),
+        repo: Some(repo_url.to_string()),
+        reason,
+        message,
+    };
+    let mut skipped: Vec<SkippedFile> = sources
+        .failed
+        .iter()
+        .filter(|(path, _)| Scope::of(path).is_target())
+        .map(|(path, err)| skip(path, SkipReason::DownloadFailed, Some(err.clone())))
+        .collect();
 
-    Ok(output.clone().contracts)
-}
+    // Everything fetched is available for import resolution, only targets get compiled and reported
+    let (targets, out_of_scope): (Vec<&String>, Vec<&String>) = repo
+        .keys()
+        .partition(|path| Scope::of(path).is_target());
+    for path in out_of_scope {
+        println!("{} (out of scope)", path);
+        skipped.push(skip(path, SkipReason::OutOfScope, None));
+    }
+    let (done, targets): (Vec<&String>, Vec<&String>) = targets
+        .into_iter()
+        .partition(|path| {
+            // Comparing needs every target in the output, unchanged ones included
+            !cli.compare_with_previous && completed.get(path.as_str()).is_some_and(|sha| sources.blob_shas.get(path.as_str()) == Some(sha))
+        });
+    if !done.is_empty() {
+        println!("Skipping {} targets an earlier run already completed at the same blob SHA", done.len());
+    }
+    skipped.extend(done.iter().map(|path| skip(path, SkipReason::AlreadyCompleted, None)));
+    // A file with nothing declared in it compiles to nothing, solc needn't see it
+    let (empty, targets): (Vec<&String>, Vec<&String>) = targets
+        .into_iter()
+        .partition(|path| SourceExtension::of(path) == Some(SourceExtension::Sol) && is_empty_source(&repo[path.as_str()]));
+    for path in empty {
+        println!("Skipping {}: declares no contract, interface, library or function", path);
+        skipped.push(skip(path, SkipReason::EmptySource, None));
+    }
 
-fn get_contracts_bytecodes(contracts: Contracts, filename: &str) -> Option<Vec<(String, String)>> {
-    // Access the contracts for the specified file name
-    if let Some(file_contracts) = contracts.get(filename) {
-        // Iterate through the contracts and retrieve the names and bytecode
-        let bytecodes: Vec<(String, String)> = file_contracts
+    // The release only, like a pragma would name it
+    let solc_version = settings
+        .solc()
+        .version()
+        .ok()
+        .map(|version| format!("{}.{}.{}", version.major, version.minor, version.patch));
+    // Without `--solc-path` each target compiles with the newest release its pragmas allow (the
+    // locked one with `--locked`) when svm has it, installed up front unless `--skip-prewarm`
+    // Each solc target's import closure is resolved once, it picks the version and is what gets compiled
+    let closures: BTreeMap<&String, BTreeMap<PathBuf, Source>> = targets
+        .iter()
+        .filter(|path| SourceExtension::of(path).map(SourceExtension::compiler) == Some(Compiler::Solc))
+        .map(|path| (*path, import_closure(path, repo)))
+        .collect();
+    let wanted: BTreeMap<&String, Version> = match cli.solc_path {
+        Some(_) => BTreeMap::new(),
+        None => closures
             .iter()
-            .filter_map(|(contract_name, contract)| {
-                contract
-                    .evm
-                    .as_ref()
-                    .and_then(|evm| {
-                        evm.bytecode.as_ref().and_then(|bytecode| match &bytecode.object {
-                            BytecodeObject::Bytecode(bytes) => {
-                                let bytecode_str = hex::encode(bytes.as_ref());
-                                Some((contract_name.clone(), bytecode_str))
-                            }
-                            BytecodeObject::Unlinked(_) => None,
-                        })
-                    })
+            .filter_map(|(path, closure)| {
+                let pinned = locked.as_ref().and_then(|locked| locked.get(path.as_str()));
+                target_solc_version(closure, pinned.map(String::as_str)).map(|version| (*path, version))
             })
+            .collect(),
+    };
+    if !cli.skip_prewarm {
+        let versions: BTreeSet<Version> = wanted.values().cloned().collect();
+        pool.install(|| prewarm_solc(&versions));
+    }
+    let svm_solcs: BTreeMap<&String, (String, PathBuf)> = wanted
+        .into_iter()
+        .filter_map(|(path, version)| svm_solc(&version).map(|solc| (path, (version.to_string(), solc))))
+        .collect();
+    let file_solc_version = |path: &String| match svm_solcs.get(path) {
+        Some((version, _)) => Some(version.clone()),
+        None => solc_version.clone(),
+    };
+    if let Some(locked) = locked {
+        let mismatched: Vec<String> = targets
+            .iter()
+            .filter_map(|path| locked.get(path.as_str()).map(|version| (path, version)))
+            .filter(|(path, version)| file_solc_version(path).as_ref() != Some(*version))
+            .map(|(path, version)| format!("{} needs {}", path, version))
             .collect();
-
-        if !bytecodes.is_empty() {
-            return Some(bytecodes);
+        if !mismatched.is_empty() {
+            anyhow::bail!("{} pins solc versions the solc in use ({}) isn't: {}, pass --solc-path to the locked one",
+                SOLC_LOCK,
+                solc_version.as_deref().unwrap_or("not found"),
+                mismatched.join(", ")
+            );
         }
     }
 
-    None
-}
+    report_pragmas(&targets, repo);
 
+    // Each batch's compiler outputs are dropped once extracted, bounding memory by the batch size
+    let batch_size = cli.batch_size.map_or(targets.len().max(1), usize::from);
+    let mut files = Vec::new();
+    for (index, batch) in targets.chunks(batch_size).enumerate() {
+        if shutdown::requested() {
+            eprintln!("Interrupted, skipping the remaining {} targets", targets.len() - index * batch_size);
+            break;
+        }
+        // Compile on the rayon pool, results keep the target order
+        let compiled: Vec<_> = pool.install(|| {
+            batch
+                .par_iter()
+                .map(|path| {
+                    // A panic in one file is reported by the hook and skipped, the rest of the repo still compiles
+                    let compiled = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                        panic::with_file(path, || {
+                            let settings = match svm_solcs.get(path) {
+                                Some((_, solc)) => CompileSettings { solc_path: Some(solc.clone()), ..settings.clone() },
+                                None => settings.clone(),
+                            };
+                            compile_target(path, repo, closures.get(path).cloned(), &settings, contest_dir, cli.emit_standard_json)
+                        })
+                    }))
+                    .unwrap_or_else(|_| Err((FileStatus::Failed, "compiler panicked".to_string())));
+                    (path, compiled)
+                })
+                .map(|(path, compiled)| match compiled {
+                    Err((FileStatus::Failed, err)) => {
+                        eprintln!("Error compiling {}: {}", path, err);
+                        if cli.format == Format::GithubActions {
+                            println!("{}", file_annotation("error", path, None, &format!("Compilation failed: {}", err)));
+                        }
+                        (path, Err((FileStatus::Failed, err)))
+                    }
+                    compiled => (path, compiled),
+                })
+                .collect()
+        });
 
-fn main() {
+        let mut batch_files = Vec::new();
+        for (path, compiled) in compiled {
+            let (output, failure) = match compiled {
+                Ok(output) => (Some(output), None),
+                Err(failure) => (None, Some(failure)),
+            };
+            let diagnostics = output
+                .as_ref()
+                .map(|output| Diagnostics::for_file(&output.errors, path))
+                .unwrap_or_default();
+            let source = &repo[path.as_str()];
+            report_diagnostics(cli, path, source, &diagnostics);
+            let license = spdx_license(source);
+            if license.is_none() {
+                println!("{} has no SPDX license identifier", path);
+            }
 
-    let contests = get_active_contests("https://code4rena.com/contests");
+            let blob_sha = sources.blob_shas.get(path.as_str());
+            let change = cli.compare_with_previous.then(|| match completed.get(path.as_str()) {
+                None => FileChange::New,
+                Some(previous) if Some(previous) == blob_sha => FileChange::Unchanged,
+                Some(_) => FileChange::Changed,
+            });
+            let library_copy = blob_sha.and_then(|sha| known_libraries.identify(sha));
+            if let Some(library) = library_copy {
+                println!("{} is an unmodified copy of {}", path, library);
+            }
 
-    // Fetch the repository's Git tree using the GitHub API
-    let owner = "code-423n4";
+            let pragma = solidity_pragma(source);
+            let mut file_result = FileResult {
+                path: path.to_string(),
+                repo: Some(repo_url.to_string()),
+                blob_sha: blob_sha.cloned(),
+                source_url: sources.urls.get(path.as_str()).cloned(),
+                library_copy: library_copy.map(String::from),
+                via_ir: settings.via_ir,
+                license,
+                pragma_lock: pragma.as_deref().map(PragmaLock::of),
+                solc_version: None,
+                pragma,
+                size: Some(source.len() as u64),
+                sloc: sloc(source),
+                status: FileStatus::Compiled,
+                status_message: None,
+                change,
+                warnings: diagnostics.warnings.len(),
+                errors: diagnostics.errors.len(),
+                contracts: contract_artifacts(output.as_ref(), path, source, &settings, diagnostics.warnings.len()),
+            };
+            file_result.classify(failure, &diagnostics, cli.strict);
+            if file_result.status != FileStatus::Skipped && SourceExtension::of(path).map(SourceExtension::compiler) != Some(Compiler::Vyper) {
+                file_result.solc_version = file_solc_version(path);
+            }
+            match file_result.status {
+                FileStatus::InterfaceOnly => skipped.push(skip(path, SkipReason::InterfaceOnly, None)),
+                FileStatus::Skipped => skipped.push(skip(path, SkipReason::UnsupportedCompiler, file_result.status_message.clone())),
+                FileStatus::Compiled | FileStatus::Failed => {}
+            }
+            if cli.contracts_only {
+                file_result.contracts.retain(ContractArtifact::is_deployable);
+            }
+            for artifact in &mut file_result.contracts {
+                format_bytecode(artifact, cli.bytecode_format, path, contest_dir);
+            }
+            batch_files.push(file_result);
+        }
 
-    for contest in contests {
-        println!("id: {} status: {} sponsor: {}",
-            contest.contest_id.unwrap_or_default(),
-            contest.status.unwrap_or_default(),
-            contest.sponsor.unwrap_or_default()
+        on_batch(&batch_files)?;
+        files.extend(batch_files);
+    }
+    if cli.compare_with_previous {
+        let count = |change: FileChange| files.iter().filter(|file| file.change == Some(change)).count();
+        println!("{} new, {} changed and {} unchanged targets since the last run",
+            count(FileChange::New),
+            count(FileChange::Changed),
+            count(FileChange::Unchanged)
         );
-        let repo_url = contest.repo.as_ref().unwrap();
-        let url_parts: Vec<&str> = repo_url.split('/').collect();
-        let repo_name = url_parts.last().unwrap();
-
-        match get_default_branch(owner, repo_name) {
-            Ok(default_branch) => {
-                println!("Default branch: {}", default_branch);
-
-                let github_api_url = "https://api.github.com/repos";
-                let api_url = format!("{}/{}/{}/git/trees/{}?recursive=1", github_api_url, owner, repo_name, default_branch);
-
-                println!("api_url: {}", api_url);
-
-                match get_contracts_urls(&api_url) {
-                    Ok(contract_data) => {
-                        for (url, filename) in contract_data {
-                            // Fetch the contract content using the contract URL
-                            // if filename != "Strings.sol" {
-                            //     continue;
-                            // }
-                            println!("// Solidity contract URL: {}", url);
-                            println!("// Solidity contract filename: {}", filename);
-                            let contract = clone_contract(&url).unwrap();
-                            let contract_content = contract.content.clone().replace("\n", "");
-                            let contract_decoded_content = b64::STANDARD.decode(contract_content).unwrap();
-                            let contract_decoded_string = String::from_utf8_lossy(&contract_decoded_content);
-                            // println!("\n\n{}", contract_decoded_string);
-                            
-                            let compiled_contracts = compile_contract(&filename, &contract_decoded_string).unwrap();
-
-                            if let Some(contracts_bytecodes) = get_contracts_bytecodes(compiled_contracts, &filename) {
-                                for (contract_name, bytecode) in contracts_bytecodes {
-                                    // println!("Contract Name: {}", contract_name);
-                                    // println!("Bytecode: {}", bytecode);
-                                }
-                            } else {
-                                println!("No contracts found in the specified file.");
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Error fetching GitHub repository contents: {}", err);
-                    }
+    }
+
+    Ok((files, skipped))
+}
+
+/// Prints `path`'s diagnostics, warnings only with `--show-warnings` or `--strict`, and annotates
+/// them for `--format github-actions`.
+fn report_diagnostics(cli: &Cli, path: &str, source: &str, diagnostics: &Diagnostics) {
+    if !diagnostics.warnings.is_empty() || !diagnostics.errors.is_empty() {
+        println!("{}: {} warnings, {} errors", path, diagnostics.warnings.len(), diagnostics.errors.len());
+    }
+    for error in &diagnostics.errors {
+        eprintln!("{}", error);
+    }
+    if cli.show_warnings || cli.strict {
+        for warning in &diagnostics.warnings {
+            eprintln!("{}", warning);
+        }
+    }
+
+    if cli.format == Format::GithubActions {
+        let warnings = diagnostics.warnings.iter().filter(|_| cli.show_warnings || cli.strict);
+        for diagnostic in diagnostics.errors.iter().chain(warnings) {
+            println!("{}", github_annotation(diagnostic, path, source));
+        }
+    }
+}
+
+/// Prints how the pragmas of `targets` constrain solc, and how many float.
+fn report_pragmas(targets: &[&String], repo: &BTreeMap<String, String>) {
+    let pragmas = pragma_report(targets.iter().map(|path| (*path, &repo[path.as_str()])));
+    if pragmas.len() > 1 {
+        println!("Mixed pragmas across {} constraints:", pragmas.len());
+    }
+    for (pragma, paths) in &pragmas {
+        println!("pragma solidity {}: {}", pragma, paths.join(", "));
+    }
+    let floating: usize = pragmas
+        .iter()
+        .filter(|(pragma, _)| PragmaLock::of(pragma) == PragmaLock::Floating)
+        .map(|(_, paths)| paths.len())
+        .sum();
+    if !pragmas.is_empty() {
+        let locked = pragmas.values().map(Vec::len).sum::<usize>() - floating;
+        println!("{} floating and {} locked pragmas", floating, locked);
+    }
+}
+
+/// Writes `artifact`'s bytecode the way `format` asks, out to a `.bin` file under `contest_dir` for raw.
+fn format_bytecode(artifact: &mut ContractArtifact, format: BytecodeFormat, path: &str, contest_dir: Option<&Path>) {
+    // The hash is always of the bare hex, so runs with different formats still diff cleanly
+    match format {
+        BytecodeFormat::Hex => {}
+        BytecodeFormat::PrefixedHex => artifact.bytecode = artifact.bytecode.take().map(|bytecode| format!("0x{}", bytecode)),
+        BytecodeFormat::Raw => {
+            if let (Some(bytecode), Some(contest_dir)) = (artifact.bytecode.take(), contest_dir) {
+                let bin_path = bytecode_path(contest_dir, path, &artifact.name);
+                let written = hex::decode(&bytecode).map_err(anyhow::Error::from).and_then(|bytes| write_binary(&bin_path, &bytes));
+                if let Err(err) = written {
+                    eprintln!("Error writing bytecode for {}:{}: {}", path, artifact.name, err);
                 }
             }
-            Err(err) => {
-                println!("Error: {:?}", err);
-                // Handle the error case
-            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// `<contest_dir>/bytecode/<path>/<contract>.bin`, written with `--bytecode-format raw`.
+fn bytecode_path(contest_dir: &Path, path: &str, contract_name: &str) -> PathBuf {
+    contest_dir.join("bytecode").join(path).join(format!("{}.bin", contract_name))
+}
+
+/// Pins the solc version of every compiled file in `<out-dir>/solc.lock`, keeping other contests' pins.
+fn write_solc_lock(out_dir: &Path, results: &[ContestResult]) -> Result<()> {
+    let path = out_dir.join(SOLC_LOCK);
+    let mut solc_lock = if path.exists() { SolcLock::read(&path)? } else { SolcLock::default() };
+    solc_lock.record(results);
+    solc_lock.write(&path)
+}
+
+/// Records where every contract came from and which files hold its artifacts.
+fn write_manifest(cli: &Cli, out_dir: &Path, results: &[ContestResult]) -> Result<()> {
+    let settings = cli.compile_settings();
+    // Repos whose foundry.toml asks for it were compiled via IR whatever the flags said
+    let via_ir_settings = CompileSettings { via_ir: true, ..settings.clone() };
+    let solc_settings = [serde_json::to_value(settings.solc_settings())?, serde_json::to_value(via_ir_settings.solc_settings())?];
+
+    let manifest = Manifest::new(results, |file| solc_settings[usize::from(file.via_ir)].clone(), |contest, file, contract| {
+        let mut outputs = Vec::new();
+        if let Some(output) = &cli.output {
+            outputs.push(output.display().to_string());
+        }
+        let artifact_dir = contest.artifact_dir(out_dir, file.repo.as_deref());
+        if settings.emits(Artifact::Ast) {
+            let ast_path = artifact_dir.join("ast").join(format!("{}.json", file.path));
+            outputs.push(ast_path.display().to_string());
+        }
+        if cli.emit_standard_json {
+            let input_path = artifact_dir.join("standard-json").join(format!("{}.json", file.path));
+            outputs.push(input_path.display().to_string());
+        }
+        if cli.save_sources {
+            outputs.push(artifact_dir.join("sources.tar.zst").display().to_string());
+        }
+        if cli.bytecode_format == BytecodeFormat::Raw && contract.bytecode_hash.is_some() {
+            outputs.push(bytecode_path(&artifact_dir, &file.path, &contract.name).display().to_string());
+        }
+        outputs
+    });
+    write_json(&out_dir.join("manifest.json"), &manifest)
+}
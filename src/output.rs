@@ -0,0 +1,1182 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use ethers_solc::CompilerOutput;
+use ethers_solc::artifacts::{Error, GasEstimates, Offsets};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use chrono::Utc;
+use clap::ValueEnum;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::awards::AwardSummary;
+use crate::compile::{
+    constructor_inputs, emitted, get_contracts_abis, get_contracts_bytecodes, get_contracts_gas_estimates, get_contracts_immutable_references,
+    get_contracts_method_identifiers, get_contracts_storage_layouts, get_contracts_unlinked_libraries, no_bytecode_reason, Artifact, CompileSettings, Diagnostics, NoBytecode,
+};
+use crate::contests::{normalize_tags, Contest};
+use crate::github::RepoRef;
+use crate::sources::{contract_kinds, ContractKind, PragmaLock};
+
+/// Bumped whenever the shape of `ScrapeResults` changes incompatibly.
+pub const RESULTS_VERSION: u32 = 1;
+
+/// Everything a scrape run produced, as written by `--output`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScrapeResults {
+    pub version: u32,
+    pub generated_at: String,
+    pub contests: Vec<ContestResult>,
+}
+
+/// `ScrapeResults` with the contests grouped under their normalized sponsor name, see `sponsor_name`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SponsorResults {
+    pub version: u32,
+    pub generated_at: String,
+    pub sponsors: BTreeMap<String, SponsorGroup>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SponsorGroup {
+    pub contests: Vec<ContestResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContestResult {
+    pub contest_id: Option<u32>,
+    pub slug: Option<String>,
+    pub sponsor: Option<String>,
+    pub repo: Option<String>,
+    /// Award pool as the contests page shows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub award: Option<String>,
+    /// Normalized categories from the contests page and the README, see `contests::normalize_tags`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The award pool in USD, with `--usd` and a known price for its coin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub award_usd: Option<f64>,
+    /// Commit each repo's sources were read at, keyed by repo URL, when it could be resolved
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commits: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub files: Vec<FileResult>,
+    /// Concrete contracts with creation code across the reported files, see `ContestResult::count_contracts`
+    #[serde(default)]
+    pub in_scope_contracts: usize,
+    /// How attractive the contest is to audit, see `priority::priority_score`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+    /// Name of its directory under `--out-dir`, the key when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Repos on hosts other than GitHub, listed but not fetched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsupported_repos: Vec<String>,
+    /// Fetched sources that add no contracts to `files`, and why
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedFile>,
+    /// What the contest paid out, with `--fetch-awards` once its leaderboard is published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awards: Option<AwardSummary>,
+    /// Why the contest couldn't be processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileResult {
+    pub path: String,
+    /// Repo the file came from, contests can span several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Git blob SHA of the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_sha: Option<String>,
+    /// GitHub API blob URL the source was downloaded from, `None` for cloned repos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Known library source this file is an unmodified copy of, see `libraries::KnownLibraries`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library_copy: Option<String>,
+    /// Compiled through the IR pipeline, by `--via-ir` or the repo's foundry.toml
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub via_ir: bool,
+    /// SPDX license identifier, `None` when the file doesn't declare one
+    #[serde(default)]
+    pub license: Option<String>,
+    /// `pragma solidity` constraint, e.g. `^0.8.0`
+    #[serde(default)]
+    pub pragma: Option<String>,
+    /// Whether `pragma` pins an exact version, `None` without a pragma
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pragma_lock: Option<PragmaLock>,
+    /// solc release the file was compiled with, `None` for Vyper and files never compiled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solc_version: Option<String>,
+    /// Bytes of source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Source lines of code, see `sources::sloc`
+    #[serde(default)]
+    pub sloc: usize,
+    /// What became of the file, with the reason in `status_message` when it failed or was skipped
+    #[serde(default)]
+    pub status: FileStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_message: Option<String>,
+    /// How the file compares to the blob SHA the state file saw last run, with `--compare-with-previous`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<FileChange>,
+    pub warnings: usize,
+    pub errors: usize,
+    pub contracts: Vec<ContractArtifact>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    /// Not seen in this contest before
+    New,
+    /// Seen with a different blob SHA
+    Changed,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// Compiled without errors
+    #[default]
+    Compiled,
+    /// solc or vyper reported errors, or couldn't be run
+    Failed,
+    /// Compiled, but only declares interfaces and abstract contracts, there's nothing to deploy
+    InterfaceOnly,
+    /// Never handed to a compiler
+    Skipped,
+}
+
+/// Why a fetched source isn't among a contest's reported contracts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// A test, script, mock or dependency, see `sources::Scope`
+    OutOfScope,
+    /// An earlier run already reported it at the same blob SHA
+    AlreadyCompleted,
+    /// Listed in the repo, but its content couldn't be downloaded or decoded
+    DownloadFailed,
+    /// The compiler available can't take it, e.g. Yul through standard JSON
+    UnsupportedCompiler,
+    /// Compiled, but only declares interfaces and abstract contracts
+    InterfaceOnly,
+    /// Declares no contract, interface, library or function, never handed to solc
+    EmptySource,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SkipReason::OutOfScope => "out of scope",
+            SkipReason::AlreadyCompleted => "already completed",
+            SkipReason::DownloadFailed => "download failed",
+            SkipReason::UnsupportedCompiler => "unsupported compiler",
+            SkipReason::InterfaceOnly => "interface only",
+            SkipReason::EmptySource => "empty source",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    /// Repo the file came from, contests can span several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    pub reason: SkipReason,
+    /// The error behind the skip, when there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractArtifact {
+    pub name: String,
+    /// Interface, abstract, library or concrete, `None` when the declaration couldn't be found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ContractKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<Value>,
+    /// The ABI's `constructor` inputs, empty when there's no explicit constructor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constructor_inputs: Option<Vec<Value>>,
+    /// Creation bytecode as `--bytecode-format` hex, `None` unless `bytecode` is emitted and the contract is
+    /// fully linked, or when it was written out as a raw `.bin` file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytecode: Option<String>,
+    /// sha256 of the hex bytecode, lets runs be compared without diffing whole bytecodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytecode_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_layout: Option<Value>,
+    /// solc's creation and per-function gas estimates, costs may be `infinite`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_estimates: Option<GasEstimates>,
+    /// 4-byte selector -> function signature
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selectors: BTreeMap<String, String>,
+    /// AST id of each immutable -> its offsets in the deployed bytecode, filled in at deploy time
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub immutable_references: BTreeMap<String, Vec<Offsets>>,
+    /// `<file>:<name>` of the libraries the bytecode still needs an address for, pass them with `--libraries`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unlinked_libraries: Vec<String>,
+}
+
+impl ScrapeResults {
+    pub fn new(contests: Vec<ContestResult>) -> Self {
+        ScrapeResults {
+            version: RESULTS_VERSION,
+            generated_at: Utc::now().to_rfc3339(),
+            contests,
+        }
+    }
+
+    /// Reads results written by `write`, compressed or not, grouped ones come back flattened.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let results: Value = serde_json::from_reader(open_reader(path)?)?;
+        let results: ScrapeResults = match results.get("sponsors") {
+            Some(_) => serde_json::from_value::<SponsorResults>(results)?.into(),
+            None => serde_json::from_value(results)?,
+        };
+        if results.version != RESULTS_VERSION {
+            anyhow::bail!("{} has results version {}, expected {}", path.display(), results.version, RESULTS_VERSION);
+        }
+
+        Ok(results)
+    }
+
+    pub fn write(&self, path: &Path, compression: Option<Compression>) -> anyhow::Result<()> {
+        write_pretty(path, compression, self)
+    }
+
+    pub fn group_by_sponsor(self) -> SponsorResults {
+        let mut sponsors: BTreeMap<String, SponsorGroup> = BTreeMap::new();
+        for contest in self.contests {
+            sponsors.entry(sponsor_name(contest.sponsor.as_deref())).or_default().contests.push(contest);
+        }
+
+        SponsorResults { version: self.version, generated_at: self.generated_at, sponsors }
+    }
+}
+
+impl SponsorResults {
+    pub fn write(&self, path: &Path, compression: Option<Compression>) -> anyhow::Result<()> {
+        write_pretty(path, compression, self)
+    }
+}
+
+impl From<SponsorResults> for ScrapeResults {
+    fn from(results: SponsorResults) -> Self {
+        ScrapeResults {
+            version: results.version,
+            generated_at: results.generated_at,
+            contests: results.sponsors.into_values().flat_map(|group| group.contests).collect(),
+        }
+    }
+}
+
+fn write_pretty(path: &Path, compression: Option<Compression>, value: &impl Serialize) -> anyhow::Result<()> {
+    let mut out = create_writer(path, compression)?;
+    serde_json::to_writer_pretty(&mut out, value)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+impl ContestResult {
+    pub fn new(contest: &Contest) -> Self {
+        ContestResult {
+            contest_id: contest.contest_id,
+            slug: contest.slug.clone(),
+            sponsor: contest.sponsor.clone(),
+            repo: contest.repos().into_iter().next(),
+            award: contest.formatted_amount.clone().or_else(|| contest.amount.clone()),
+            tags: normalize_tags(contest.tags.clone().unwrap_or_default()),
+            award_usd: None,
+            commits: BTreeMap::new(),
+            start_time: contest.start_time.clone(),
+            end_time: contest.end_time.clone(),
+            files: Vec::new(),
+            in_scope_contracts: 0,
+            priority: None,
+            directory: None,
+            unsupported_repos: Vec::new(),
+            skipped: Vec::new(),
+            awards: None,
+            error: None,
+        }
+    }
+
+    /// A repo compiled on its own through the `repo` subcommand, keyed by `owner/name`.
+    pub fn for_repo(repo: &RepoRef) -> Self {
+        ContestResult {
+            contest_id: None,
+            slug: Some(format!("{}/{}", repo.owner, repo.name)),
+            sponsor: None,
+            repo: Some(repo.url()),
+            award: None,
+            tags: Vec::new(),
+            award_usd: None,
+            commits: BTreeMap::new(),
+            start_time: None,
+            end_time: None,
+            files: Vec::new(),
+            in_scope_contracts: 0,
+            priority: None,
+            directory: None,
+            unsupported_repos: Vec::new(),
+            skipped: Vec::new(),
+            awards: None,
+            error: None,
+        }
+    }
+
+    /// In-scope SLOC, every reported file is an audit target.
+    pub fn sloc(&self) -> usize {
+        self.files.iter().map(|file| file.sloc).sum()
+    }
+
+    /// Reported files that are unmodified copies of a known library.
+    pub fn library_copies(&self) -> usize {
+        self.files.iter().filter(|file| file.library_copy.is_some()).count()
+    }
+
+    /// How many files were skipped for each reason.
+    pub fn skip_counts(&self) -> BTreeMap<SkipReason, usize> {
+        let mut counts = BTreeMap::new();
+        for skipped in &self.skipped {
+            *counts.entry(skipped.reason).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Concrete contracts with non-empty bytecode, every reported file is an audit target.
+    pub fn count_contracts(&self) -> usize {
+        self.files
+            .iter()
+            .flat_map(|file| &file.contracts)
+            .filter(|contract| contract.is_deployable())
+            .count()
+    }
+
+    /// `<out-dir>/<contest>`, with a subdirectory for every repo after the contest's first.
+    pub fn artifact_dir(&self, out_dir: &Path, repo: Option<&str>) -> PathBuf {
+        let dir = out_dir.join(self.directory.clone().unwrap_or_else(|| self.key()));
+        match repo {
+            Some(repo) if Some(repo) != self.repo.as_deref() => dir.join(repo.rsplit('/').next().unwrap_or(repo)),
+            _ => dir,
+        }
+    }
+
+    /// Stable identity of a contest across runs.
+    pub fn key(&self) -> String {
+        match (self.contest_id, &self.slug) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(slug)) => slug.clone(),
+            (None, None) => self.repo.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl FileResult {
+    /// A file that's only listed, for `--format index`.
+    pub fn indexed(path: String, repo: &str, blob_sha: Option<String>, size: u64) -> Self {
+        FileResult {
+            path,
+            repo: Some(repo.to_string()),
+            blob_sha,
+            source_url: None,
+            library_copy: None,
+            via_ir: false,
+            license: None,
+            pragma: None,
+            pragma_lock: None,
+            solc_version: None,
+            size: Some(size),
+            sloc: 0,
+            status: FileStatus::Skipped,
+            status_message: Some("--format index doesn't compile".to_string()),
+            change: None,
+            warnings: 0,
+            errors: 0,
+            contracts: Vec::new(),
+        }
+    }
+
+    /// Sets the status from how compiling went, given `contracts` is already filled in.
+    ///
+    /// Errors fail the file, and so do warnings with `strict`. A file whose contracts are all
+    /// interfaces or abstract is interface-only.
+    pub fn classify(&mut self, failure: Option<(FileStatus, String)>, diagnostics: &Diagnostics, strict: bool) {
+        let joined = |diagnostics: &[Error]| diagnostics.iter().map(|error| error.message.as_str()).collect::<Vec<_>>().join("\n");
+        let interface_only = !self.contracts.is_empty()
+            && self.contracts.iter().all(|contract| matches!(contract.kind, Some(ContractKind::Interface | ContractKind::Abstract)));
+        (self.status, self.status_message) = match failure {
+            Some((status, message)) => (status, Some(message)),
+            None if !diagnostics.errors.is_empty() => (FileStatus::Failed, Some(joined(&diagnostics.errors))),
+            None if strict && !diagnostics.warnings.is_empty() => (FileStatus::Failed, Some(joined(&diagnostics.warnings))),
+            None if interface_only => (FileStatus::InterfaceOnly, None),
+            None => (FileStatus::Compiled, None),
+        };
+    }
+}
+
+/// The artifacts of every contract compiled from `path`, sorted by name, with only the
+/// outputs `settings` selects filled in.
+///
+/// Prints each contract, and why the file has no deployable bytecode when it hasn't.
+pub fn contract_artifacts(output: Option<&CompilerOutput>, path: &str, source: &str, settings: &CompileSettings, warnings: usize) -> Vec<ContractArtifact> {
+    let contract_names: Vec<String> = output
+        .and_then(|output| output.contracts.get(path))
+        .map(|contracts| contracts.keys().cloned().collect())
+        .unwrap_or_default();
+    let mut abis = emitted(settings, Artifact::Abi, output, |contracts| get_contracts_abis(contracts, path));
+    let mut bytecodes = emitted(settings, Artifact::Bytecode, output, |contracts| get_contracts_bytecodes(contracts, path));
+    let mut layouts = emitted(settings, Artifact::StorageLayout, output, |contracts| get_contracts_storage_layouts(contracts, path));
+    let mut selectors = emitted(settings, Artifact::MethodIdentifiers, output, |contracts| get_contracts_method_identifiers(contracts, path));
+    let mut gas_estimates = emitted(settings, Artifact::GasEstimates, output, |contracts| get_contracts_gas_estimates(contracts, path));
+    let mut immutables = emitted(settings, Artifact::ImmutableReferences, output, |contracts| get_contracts_immutable_references(contracts, path));
+    let mut unlinked = emitted(settings, Artifact::Bytecode, output, |contracts| get_contracts_unlinked_libraries(contracts, path));
+    let reason = if settings.emits(Artifact::Bytecode) {
+        no_bytecode_reason(output, path)
+    } else {
+        // Without bytecode only a missing output or no contracts at all is worth reporting
+        no_bytecode_reason(output, path).filter(|reason| matches!(reason, NoBytecode::CompileFailed | NoBytecode::NoContracts))
+    };
+    if let Some(reason) = reason {
+        println!("No deployable bytecode in {}: {}", path, reason);
+    }
+
+    let kinds = contract_kinds(source);
+    let mut artifacts: Vec<ContractArtifact> = contract_names
+        .into_iter()
+        .map(|contract_name| {
+            let bytecode = bytecodes.remove(&contract_name);
+            let kind = kinds.get(&contract_name).copied();
+            let kind_label = kind.map_or("unknown", ContractKind::as_str);
+            match &bytecode {
+                Some(bytecode) => println!("{}:{} ({}, {} bytes, {} warnings)", path, contract_name, kind_label, bytecode.len() / 2, warnings),
+                None => println!("{}:{} ({}, {} warnings)", path, contract_name, kind_label, warnings),
+            }
+            // println!("Bytecode: {}", bytecode);
+            let mut artifact = ContractArtifact::new(contract_name, bytecode);
+            artifact.kind = kind;
+            artifact.abi = abis.remove(&artifact.name);
+            artifact.constructor_inputs = artifact.abi.as_ref().map(constructor_inputs);
+            artifact.storage_layout = layouts.remove(&artifact.name);
+            artifact.gas_estimates = gas_estimates.remove(&artifact.name);
+            artifact.selectors = selectors.remove(&artifact.name).unwrap_or_default();
+            artifact.immutable_references = immutables.remove(&artifact.name).unwrap_or_default();
+            artifact.unlinked_libraries = unlinked.remove(&artifact.name).unwrap_or_default();
+            if !artifact.unlinked_libraries.is_empty() {
+                println!("{}:{} needs addresses for {}, pass them with --libraries", path, artifact.name, artifact.unlinked_libraries.join(", "));
+            }
+            artifact
+        })
+        .collect();
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    artifacts
+}
+
+impl ContractArtifact {
+    pub fn new(name: String, bytecode: Option<String>) -> Self {
+        let bytecode_hash = bytecode.as_ref().map(|bytecode| hex::encode(Sha256::digest(bytecode.as_bytes())));
+        ContractArtifact {
+            name,
+            kind: None,
+            abi: None,
+            constructor_inputs: None,
+            bytecode,
+            bytecode_hash,
+            storage_layout: None,
+            gas_estimates: None,
+            selectors: BTreeMap::new(),
+            immutable_references: BTreeMap::new(),
+            unlinked_libraries: Vec::new(),
+        }
+    }
+
+    /// What's wrong with the emitted bytecode, if anything: it must be even-length hex that decodes,
+    /// and non-empty for contracts and libraries. Raw `.bin` bytecode isn't checked.
+    pub fn bytecode_problem(&self) -> Option<&'static str> {
+        let bytecode = self.bytecode.as_deref()?;
+        let bytecode = bytecode.strip_prefix("0x").unwrap_or(bytecode);
+        if bytecode.is_empty() {
+            return matches!(self.kind, Some(ContractKind::Concrete | ContractKind::Library)).then_some("empty bytecode");
+        }
+        if bytecode.len() % 2 != 0 {
+            return Some("odd-length hex");
+        }
+        hex::decode(bytecode).err().map(|_| "not valid hex")
+    }
+
+    /// A concrete contract, or one whose declaration wasn't found, with creation code.
+    pub fn is_deployable(&self) -> bool {
+        matches!(self.kind, None | Some(ContractKind::Concrete)) && self.has_bytecode()
+    }
+
+    /// Whether solc produced creation code, also when it went to a raw `.bin` file.
+    pub fn has_bytecode(&self) -> bool {
+        // Interfaces and abstract contracts compile to an empty object, whose hash is that of ""
+        const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        self.bytecode_hash.as_deref().is_some_and(|hash| hash != EMPTY_HASH)
+    }
+}
+
+/// Directory names for contests given as (slug, key): the sanitized slug, with `-<key>` appended
+/// when two contests' slugs end up the same, or the key when there's no usable slug.
+pub fn directory_names<'a>(contests: impl IntoIterator<Item = (Option<&'a str>, String)>) -> Vec<String> {
+    let named: Vec<(Option<String>, String)> = contests
+        .into_iter()
+        .map(|(slug, key)| (slug.and_then(sanitize_dir_name), key))
+        .collect();
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for slug in named.iter().filter_map(|(slug, _)| slug.as_deref()) {
+        *counts.entry(slug).or_default() += 1;
+    }
+
+    named
+        .iter()
+        .map(|(slug, key)| match slug {
+            Some(slug) if counts[slug.as_str()] > 1 => format!("{}-{}", slug, key),
+            Some(slug) => slug.clone(),
+            None => sanitize_dir_name(key).unwrap_or_else(|| key.clone()),
+        })
+        .collect()
+}
+
+/// A sponsor's name as a lowercase directory-safe key, so `Lybra Finance` and `lybra finance` group together.
+pub fn sponsor_name(sponsor: Option<&str>) -> String {
+    sponsor
+        .and_then(sanitize_dir_name)
+        .map_or_else(|| "unknown-sponsor".to_string(), |name| name.to_lowercase())
+}
+
+/// Keeps ASCII letters, digits, `.`, `_` and `-`, anything else becomes `-`.
+fn sanitize_dir_name(name: &str) -> Option<String> {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '-' })
+        .collect();
+    // No hidden directories and no `..`
+    let sanitized = sanitized.trim_matches(|c| c == '.' || c == '-');
+
+    (!sanitized.is_empty()).then(|| sanitized.to_string())
+}
+
+/// Where every contract in a run came from, written to `<out-dir>/manifest.json`.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub generated_at: String,
+    /// Contest key -> its directory under `--out-dir`
+    pub directories: BTreeMap<String, String>,
+    pub contracts: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub contest_id: Option<u32>,
+    pub repo: Option<String>,
+    pub commit: Option<String>,
+    pub path: String,
+    pub blob_sha: Option<String>,
+    pub contract: String,
+    pub solc_version: Option<String>,
+    /// Standard JSON settings the contract was compiled with
+    pub settings: Value,
+    /// Files holding this contract's artifacts
+    pub outputs: Vec<String>,
+}
+
+impl Manifest {
+    /// `settings` gives the solc settings a file was compiled with, `outputs` the files written for a contract.
+    /// Each entry's solc version is the one its file was compiled with, see `FileResult::solc_version`.
+    pub fn new(
+        contests: &[ContestResult],
+        settings: impl Fn(&FileResult) -> Value,
+        outputs: impl Fn(&ContestResult, &FileResult, &ContractArtifact) -> Vec<String>,
+    ) -> Self {
+        let contracts = contests
+            .iter()
+            .flat_map(|contest| contest.files.iter().map(move |file| (contest, file)))
+            .flat_map(|(contest, file)| file.contracts.iter().map(move |contract| (contest, file, contract)))
+            .map(|(contest, file, contract)| ManifestEntry {
+                    contest_id: contest.contest_id,
+                    repo: file.repo.clone().or_else(|| contest.repo.clone()),
+                    commit: file.repo.as_ref().and_then(|repo| contest.commits.get(repo)).cloned(),
+                    path: file.path.clone(),
+                    blob_sha: file.blob_sha.clone(),
+                    contract: contract.name.clone(),
+                    solc_version: file.solc_version.clone(),
+                    settings: settings(file),
+                    outputs: outputs(contest, file, contract),
+            })
+            .collect();
+
+        let directories = contests
+            .iter()
+            .filter_map(|contest| Some((contest.key(), contest.directory.clone()?)))
+            .collect();
+
+        Manifest { generated_at: Utc::now().to_rfc3339(), directories, contracts }
+    }
+}
+
+/// `--format index`: every contest's in-scope files and their sizes, nothing compiled.
+#[derive(Debug, Serialize)]
+pub struct ScrapeIndex<'a> {
+    pub generated_at: String,
+    pub contests: Vec<ContestIndex<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContestIndex<'a> {
+    pub contest_id: Option<u32>,
+    pub slug: Option<&'a str>,
+    pub sponsor: Option<&'a str>,
+    pub files: Vec<IndexedFile<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexedFile<'a> {
+    pub repo: Option<&'a str>,
+    pub path: &'a str,
+    pub size: u64,
+}
+
+impl<'a> ScrapeIndex<'a> {
+    pub fn new(contests: &'a [ContestResult]) -> Self {
+        let contests = contests
+            .iter()
+            .map(|contest| ContestIndex {
+                contest_id: contest.contest_id,
+                slug: contest.slug.as_deref(),
+                sponsor: contest.sponsor.as_deref(),
+                files: contest
+                    .files
+                    .iter()
+                    .map(|file| IndexedFile {
+                        repo: file.repo.as_deref(),
+                        path: &file.path,
+                        size: file.size.unwrap_or_default(),
+                    })
+                    .collect(),
+                error: contest.error.as_deref(),
+            })
+            .collect();
+
+        ScrapeIndex { generated_at: Utc::now().to_rfc3339(), contests }
+    }
+
+    /// Writes to `path`, or stdout when no path is given.
+    pub fn write(&self, path: Option<&Path>, compression: Option<Compression>) -> anyhow::Result<()> {
+        let mut out = match path {
+            Some(path) => create_writer(path, compression)?,
+            None => compress(Box::new(std::io::stdout()), compression)?,
+        };
+        serde_json::to_writer_pretty(&mut out, self)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+
+        Ok(())
+    }
+}
+
+/// `--format table`: one aligned row per contest for reading in a terminal.
+pub fn contests_table(contests: &[ContestResult]) -> comfy_table::Table {
+    // The USD column only shows up with `--usd`
+    let usd = contests.iter().any(|contest| contest.award_usd.is_some());
+    let mut table = comfy_table::Table::new();
+    let mut header = vec!["id", "sponsor", "award"];
+    if usd {
+        header.push("award (USD)");
+    }
+    header.extend(["ends in", "repo"]);
+    table
+        .load_preset(comfy_table::presets::UTF8_FULL_CONDENSED)
+        .set_header(header);
+    for contest in contests {
+        let mut row = vec![
+            contest.key(),
+            contest.sponsor.clone().unwrap_or_default(),
+            contest.award.clone().unwrap_or_default(),
+        ];
+        if usd {
+            row.push(contest.award_usd.map(|award_usd| format!("${:.0}", award_usd)).unwrap_or_default());
+        }
+        row.extend([ends_in(contest.end_time.as_deref()), contest.repo.clone().unwrap_or_default()]);
+        table.add_row(row);
+    }
+
+    table
+}
+
+/// Longest message Discord accepts, in characters.
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// One line per contest, packed into as few messages of at most `limit` characters as fit.
+///
+/// Lines are never split across messages, one longer than `limit` on its own is cut short.
+pub fn discord_messages(contests: &[ContestResult], limit: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut message = String::new();
+    for contest in contests {
+        let ends_in = match ends_in(contest.end_time.as_deref()) {
+            ends_in if ends_in.is_empty() || ends_in == "ended" => ends_in,
+            ends_in => format!("{} left", ends_in),
+        };
+        let sponsor = format!("**{}**", discord_escape(contest.sponsor.as_deref().unwrap_or(&contest.key())));
+        let fields: Vec<String> = [sponsor, contest.award.clone().unwrap_or_default(), ends_in]
+            .into_iter()
+            .filter(|field| !field.is_empty())
+            .collect();
+        let line: String = format!("- {}", fields.join(" · ")).chars().take(limit).collect();
+
+        let separator = usize::from(!message.is_empty());
+        if message.chars().count() + separator + line.chars().count() > limit {
+            messages.push(std::mem::take(&mut message));
+        }
+        if !message.is_empty() {
+            message.push('\n');
+        }
+        message.push_str(&line);
+    }
+    if !message.is_empty() {
+        messages.push(message);
+    }
+
+    messages
+}
+
+/// `--format markdown`: a scope report for the contest, its details and every in-scope file with
+/// its SLOC and contracts.
+pub fn markdown_report(contest: &ContestResult) -> String {
+    let mut report = format!("# {}\n\n", markdown_escape(contest.sponsor.as_deref().unwrap_or(&contest.key())));
+    let mut details = Vec::new();
+    if let Some(award) = &contest.award {
+        match contest.award_usd {
+            Some(award_usd) => details.push(format!("- **Award:** {} (${:.0})", markdown_escape(award), award_usd)),
+            None => details.push(format!("- **Award:** {}", markdown_escape(award))),
+        }
+    }
+    if let Some(start_time) = &contest.start_time {
+        details.push(format!("- **Starts:** {}", start_time));
+    }
+    if let Some(end_time) = &contest.end_time {
+        details.push(format!("- **Ends:** {}", end_time));
+    }
+    let repos: BTreeSet<&str> = contest.repo.iter().chain(contest.commits.keys()).map(String::as_str).collect();
+    for repo in &repos {
+        match contest.commits.get(*repo) {
+            Some(commit) => details.push(format!("- **Repo:** {} at `{}`", repo, commit)),
+            None => details.push(format!("- **Repo:** {}", repo)),
+        }
+    }
+    if !contest.tags.is_empty() {
+        details.push(format!("- **Tags:** {}", contest.tags.join(", ")));
+    }
+    if !details.is_empty() {
+        report.push_str(&details.join("\n"));
+        report.push_str("\n\n");
+    }
+
+    report.push_str("## Scope\n\n");
+    if let Some(error) = &contest.error {
+        report.push_str(&format!("The contest couldn't be processed: {}\n", markdown_escape(error)));
+        return report;
+    }
+    // Files are told apart by repo only when the contest spans several
+    let by_repo = contest.files.iter().filter_map(|file| file.repo.as_deref()).collect::<BTreeSet<_>>().len() > 1;
+    report.push_str(if by_repo { "| File | Repo | SLOC | Contracts |\n|---|---|--:|--:|\n" } else { "| File | SLOC | Contracts |\n|---|--:|--:|\n" });
+    for file in &contest.files {
+        // Code spans show backslashes as they are, only the cell-ending `|` needs one
+        let path = format!("`{}`", file.path.replace('|', "\\|"));
+        let repo = file.repo.as_deref().map(|repo| repo.rsplit('/').next().unwrap_or(repo)).unwrap_or_default();
+        match by_repo {
+            true => report.push_str(&format!("| {} | {} | {} | {} |\n", path, markdown_escape(repo), file.sloc, file.contracts.len())),
+            false => report.push_str(&format!("| {} | {} | {} |\n", path, file.sloc, file.contracts.len())),
+        }
+    }
+    let contracts: usize = contest.files.iter().map(|file| file.contracts.len()).sum();
+    match by_repo {
+        true => report.push_str(&format!("| **Total** | | **{}** | **{}** |\n", contest.sloc(), contracts)),
+        false => report.push_str(&format!("| **Total** | **{}** | **{}** |\n", contest.sloc(), contracts)),
+    }
+
+    let skipped: Vec<String> = contest.skip_counts().iter().map(|(reason, count)| format!("{} {}", count, reason)).collect();
+    if !skipped.is_empty() {
+        report.push_str(&format!("\nNot in the table: {}.\n", skipped.join(", ")));
+    }
+
+    report
+}
+
+/// Backslashes the characters that would end a table cell or start formatting.
+/// `<out_dir>/<contest>.md` for every contest, next to its artifact directory.
+///
+/// The report's directory is created for it, a contest grouped by sponsor that produced no
+/// artifacts has none yet.
+pub fn write_markdown_reports(out_dir: &Path, results: &[ContestResult]) -> anyhow::Result<()> {
+    for contest in results {
+        let path = out_dir.join(format!("{}.md", contest.directory.clone().unwrap_or_else(|| contest.key())));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, markdown_report(contest)).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+fn markdown_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '|' | '*' | '_' | '`' | '\\' | '[' | ']' | '<' | '>' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// Backslashes the characters Discord markdown would otherwise format with.
+fn discord_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '*' | '_' | '~' | '`' | '|' | '>' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// Time left until `end_time` to the minute, `ended` once it's past.
+fn ends_in(end_time: Option<&str>) -> String {
+    let Some(end_time) = end_time.and_then(|end_time| chrono::DateTime::parse_from_rfc3339(end_time).ok()) else {
+        return String::new();
+    };
+    match (end_time.with_timezone(&Utc) - Utc::now()).to_std() {
+        Ok(left) => humantime::format_duration(std::time::Duration::from_secs(left.as_secs() / 60 * 60)).to_string(),
+        Err(_) => "ended".to_string(),
+    }
+}
+
+/// A contract with its contest and file context, one per line in `--format jsonl` and what `serve`
+/// answers `/contests/:id/contracts` with.
+#[derive(Debug, Serialize)]
+pub struct ContractRecord<'a> {
+    pub contest_id: Option<u32>,
+    pub slug: Option<&'a str>,
+    pub sponsor: Option<&'a str>,
+    pub repo: Option<&'a str>,
+    pub path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library_copy: Option<&'a str>,
+    pub license: Option<&'a str>,
+    pub pragma: Option<&'a str>,
+    #[serde(flatten)]
+    pub contract: &'a ContractArtifact,
+}
+
+/// Newline delimited JSON sink, every record is flushed as soon as it's written.
+pub struct JsonLines {
+    out: Box<dyn Write + Send>,
+}
+
+impl JsonLines {
+    /// Writes to `path`, or stdout when no path is given.
+    pub fn create(path: Option<&Path>, compression: Option<Compression>) -> anyhow::Result<Self> {
+        let out: Box<dyn Write + Send> = match path {
+            Some(path) => create_writer(path, compression)?,
+            None => compress(Box::new(std::io::stdout()), compression)?,
+        };
+
+        Ok(JsonLines { out })
+    }
+
+    /// Writes a record for every contract in `files`, taking the contest context from `contest`.
+    pub fn write_files(&mut self, contest: &ContestResult, files: &[FileResult]) -> anyhow::Result<()> {
+        for record in ContractRecord::all(contest, files) {
+            serde_json::to_writer(&mut self.out, &record)?;
+            self.out.write_all(b"\n")?;
+            self.out.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ContractRecord<'a> {
+    /// A record for every contract in `files`, taking the contest context from `contest`.
+    pub fn all(contest: &'a ContestResult, files: &'a [FileResult]) -> impl Iterator<Item = ContractRecord<'a>> {
+        files.iter().flat_map(move |file| {
+            file.contracts.iter().map(move |contract| ContractRecord {
+                contest_id: contest.contest_id,
+                slug: contest.slug.as_deref(),
+                sponsor: contest.sponsor.as_deref(),
+                repo: file.repo.as_deref().or(contest.repo.as_deref()),
+                path: &file.path,
+                source_url: file.source_url.as_deref(),
+                library_copy: file.library_copy.as_deref(),
+                license: file.license.as_deref(),
+                pragma: file.pragma.as_deref(),
+                contract,
+            })
+        })
+    }
+}
+
+/// Codec for `--output` and the saved source archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Wraps `out` in an encoder that finishes the stream when dropped.
+fn compress(out: Box<dyn Write + Send>, compression: Option<Compression>) -> std::io::Result<Box<dyn Write + Send>> {
+    Ok(match compression {
+        None => out,
+        Some(Compression::Gzip) => Box::new(GzEncoder::new(out, flate2::Compression::default())),
+        Some(Compression::Zstd) => Box::new(zstd::Encoder::new(out, 0)?.auto_finish()),
+    })
+}
+
+fn create_writer(path: &Path, compression: Option<Compression>) -> std::io::Result<Box<dyn Write + Send>> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    compress(Box::new(file), compression)
+}
+
+/// Opens `path` for reading, decompressing it when it starts with a gzip or zstd header.
+fn open_reader(path: &Path) -> std::io::Result<Box<dyn Read>> {
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let header = file.fill_buf()?;
+    Ok(if header.starts_with(&GZIP_MAGIC) {
+        Box::new(GzDecoder::new(file))
+    } else if header.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::with_buffer(file)?)
+    } else {
+        Box::new(file)
+    })
+}
+
+/// Writes every source in `files` into a single `.tar.zst` at `path`.
+pub fn write_source_archive(path: &Path, files: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let encoder = zstd::Encoder::new(std::fs::File::create(path)?, 0)?;
+    let mut archive = tar::Builder::new(encoder);
+    for (source_path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        archive.append_data(&mut header, source_path, content.as_bytes())?;
+    }
+    archive.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+/// Writes `bytes` to `path`, creating any missing parent directories.
+pub fn write_binary(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+/// Every emitted ABI across `contests`, keyed by contract name.
+///
+/// A name declared more than once is keyed `<path>:<name>` for each declaration instead,
+/// `<contest>/<path>:<name>` when the same path is in several contests, and
+/// `<contest>/<repo>/<path>:<name>` when it's in several repos of one contest.
+pub fn combined_abi(contests: &[ContestResult]) -> BTreeMap<String, &Value> {
+    let abis: Vec<(String, &str, &str, &str, &Value)> = contests
+        .iter()
+        .flat_map(|contest| {
+            contest.files.iter().flat_map(move |file| {
+                // Repos are told apart by name, the way their artifact directories are
+                let repo = file.repo.as_deref().or(contest.repo.as_deref()).unwrap_or_default();
+                let repo = repo.rsplit('/').next().unwrap_or(repo);
+                file.contracts
+                    .iter()
+                    .filter_map(move |contract| Some((contest.key(), repo, file.path.as_str(), contract.name.as_str(), contract.abi.as_ref()?)))
+            })
+        })
+        .collect();
+    let mut names: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut qualified: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    let mut in_contest: BTreeMap<(&str, &str, &str), usize> = BTreeMap::new();
+    for (contest, _, path, name, _) in &abis {
+        *names.entry(name).or_default() += 1;
+        *qualified.entry((path, name)).or_default() += 1;
+        *in_contest.entry((contest, path, name)).or_default() += 1;
+    }
+
+    abis.iter()
+        .map(|(contest, repo, path, name, abi)| {
+            let key = match (names[name], qualified[&(*path, *name)], in_contest[&(contest.as_str(), *path, *name)]) {
+                (1, _, _) => name.to_string(),
+                (_, 1, _) => format!("{}:{}", path, name),
+                (_, _, 1) => format!("{}/{}:{}", contest, path, name),
+                _ => format!("{}/{}/{}:{}", contest, repo, path, name),
+            };
+            (key, *abi)
+        })
+        .collect()
+}
+
+/// Writes `value` to `path` as JSON, creating any missing parent directories.
+pub fn write_json(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer(&mut writer, value)?;
+    // Dropping the writer would swallow an error flushing the last of it
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contest(sponsor: &str) -> ContestResult {
+        let repo = RepoRef { owner: "code-423n4".to_string(), name: "2024-01-example".to_string(), reference: None };
+        ContestResult { sponsor: Some(sponsor.to_string()), ..ContestResult::for_repo(&repo) }
+    }
+
+    #[test]
+    fn discord_messages_stay_within_the_limit() {
+        assert!(discord_messages(&[], DISCORD_MESSAGE_LIMIT).is_empty());
+
+        let contests: Vec<ContestResult> = (0..200).map(|index| contest(&format!("Sponsor {}", index))).collect();
+        let messages = discord_messages(&contests, DISCORD_MESSAGE_LIMIT);
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| message.chars().count() <= DISCORD_MESSAGE_LIMIT));
+        assert_eq!(messages.iter().map(|message| message.lines().count()).sum::<usize>(), contests.len());
+    }
+
+    #[test]
+    fn discord_messages_cut_a_line_longer_than_the_limit() {
+        let messages = discord_messages(&[contest(&"a".repeat(50))], 20);
+        assert_eq!(messages, [format!("- **{}", "a".repeat(16))]);
+    }
+
+    #[test]
+    fn discord_messages_split_at_the_boundary() {
+        // `- **abc**` is 9 characters, two lines and their newline are exactly 19
+        let contests = [contest("abc"), contest("abc"), contest("abc")];
+        assert_eq!(discord_messages(&contests, 19), ["- **abc**\n- **abc**", "- **abc**"]);
+        assert_eq!(discord_messages(&contests, 18), ["- **abc**", "- **abc**", "- **abc**"]);
+    }
+
+    #[test]
+    fn colliding_slugs_get_their_key_appended() {
+        let names = directory_names([(Some("2024-01-Lybra Finance"), "301".to_string()), (Some("2024-01-Lybra/Finance"), "302".to_string()), (Some("other"), "303".to_string())]);
+        assert_eq!(names, ["2024-01-Lybra-Finance-301", "2024-01-Lybra-Finance-302", "other"]);
+    }
+
+    #[test]
+    fn unusable_slugs_fall_back_to_the_key() {
+        assert_eq!(sanitize_dir_name("../.."), None);
+        assert_eq!(sanitize_dir_name("日本"), None);
+        assert_eq!(sanitize_dir_name(".hidden"), Some("hidden".to_string()));
+
+        let names = directory_names([(Some("日本"), "301".to_string()), (None, "302".to_string())]);
+        assert_eq!(names, ["301", "302"]);
+    }
+
+    #[test]
+    fn combined_abi_tells_repos_of_a_contest_apart() {
+        let mut contest = contest("Example");
+        for repo in ["https://github.com/code-423n4/2024-01-core", "https://github.com/code-423n4/2024-01-periphery"] {
+            let mut file = FileResult::indexed("src/Vault.sol".to_string(), repo, None, 0);
+            let mut vault = ContractArtifact::new("Vault".to_string(), None);
+            vault.abi = Some(serde_json::json!([{ "type": "function", "name": repo }]));
+            file.contracts.push(vault);
+            contest.files.push(file);
+        }
+
+        let keys: Vec<String> = combined_abi(std::slice::from_ref(&contest)).into_keys().collect();
+        let key = contest.key();
+        assert_eq!(keys, [format!("{}/2024-01-core/src/Vault.sol:Vault", key), format!("{}/2024-01-periphery/src/Vault.sol:Vault", key)]);
+    }
+
+    #[test]
+    fn classifies_by_failure_then_diagnostics_then_contract_kinds() {
+        let mut file = FileResult::indexed("src/IVault.sol".to_string(), "https://github.com/code-423n4/2024-01-example", None, 0);
+        let mut interface = ContractArtifact::new("IVault".to_string(), None);
+        interface.kind = Some(ContractKind::Interface);
+        file.contracts.push(interface);
+
+        file.classify(Some((FileStatus::Skipped, "unsupported".to_string())), &Diagnostics::default(), false);
+        assert_eq!((file.status, file.status_message.as_deref()), (FileStatus::Skipped, Some("unsupported")));
+        file.classify(None, &Diagnostics::default(), true);
+        assert_eq!((file.status, file.status_message.as_deref()), (FileStatus::InterfaceOnly, None));
+
+        file.contracts[0].kind = Some(ContractKind::Concrete);
+        file.classify(None, &Diagnostics::default(), false);
+        assert_eq!((file.status, file.status_message.as_deref()), (FileStatus::Compiled, None));
+    }
+
+    #[test]
+    fn writes_sponsor_grouped_reports_of_failed_contests() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut failed = contest("Example");
+        // `--group-by sponsor` nests each contest's directory under its sponsor's
+        failed.directory = Some(format!("{}/2024-01-example", sponsor_name(failed.sponsor.as_deref())));
+        failed.error = Some("no sources".to_string());
+
+        write_markdown_reports(out_dir.path(), std::slice::from_ref(&failed)).unwrap();
+        let report = std::fs::read_to_string(out_dir.path().join(format!("{}.md", failed.directory.as_deref().unwrap()))).unwrap();
+        assert_eq!(report, markdown_report(&failed));
+    }
+
+    #[test]
+    fn manifest_records_each_files_solc_version() {
+        let mut contest = contest("Example");
+        let repo = contest.repo.clone().unwrap();
+        for (path, version) in [("src/Old.sol", "0.7.6"), ("src/New.sol", "0.8.30")] {
+            let mut file = FileResult::indexed(path.to_string(), &repo, None, 0);
+            file.solc_version = Some(version.to_string());
+            file.contracts.push(ContractArtifact::new(path.trim_start_matches("src/").trim_end_matches(".sol").to_string(), None));
+            contest.files.push(file);
+        }
+
+        let manifest = Manifest::new(&[contest], |_| Value::Null, |_, _, _| Vec::new());
+        let versions: Vec<(&str, Option<&str>)> =
+            manifest.contracts.iter().map(|entry| (entry.contract.as_str(), entry.solc_version.as_deref())).collect();
+        assert_eq!(versions, [("Old", Some("0.7.6")), ("New", Some("0.8.30"))]);
+    }
+}
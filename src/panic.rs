@@ -0,0 +1,47 @@
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+/// Contest being processed, shared by every thread.
+static CONTEST: Mutex<Option<String>> = Mutex::new(None);
+
+thread_local! {
+    /// File the current thread is working on.
+    static FILE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Prints the contest and file being processed before the default panic message.
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let contest = CONTEST.lock().map(|contest| contest.clone()).unwrap_or_default();
+        let file = FILE.with(|file| file.borrow().clone());
+        eprintln!(
+            "Panicked while processing contest {}, file {}",
+            contest.as_deref().unwrap_or("<none>"),
+            file.as_deref().unwrap_or("<none>")
+        );
+        default_hook(info);
+    }));
+}
+
+pub fn set_contest(key: Option<&str>) {
+    if let Ok(mut contest) = CONTEST.lock() {
+        *contest = key.map(String::from);
+    }
+}
+
+/// Restores the previous file when dropped, unwinding included.
+struct FileGuard(Option<String>);
+
+impl Drop for FileGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        FILE.with(|file| *file.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` with `path` recorded as the current thread's file.
+pub fn with_file<T>(path: &str, f: impl FnOnce() -> T) -> T {
+    let _guard = FileGuard(FILE.with(|file| file.replace(Some(path.to_string()))));
+    f()
+}
@@ -0,0 +1,45 @@
+use anyhow::Context;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::contests::Contest;
+
+/// Coins priced without a `--prices` file, the stablecoins pools are usually paid in.
+const STABLECOINS: [&str; 4] = ["USD", "USDC", "USDT", "DAI"];
+
+/// USD price of each award coin, keyed by the coin in uppercase. Nothing is looked up online, a coin
+/// is either a stablecoin or given in the price file.
+#[derive(Debug, Clone)]
+pub struct UsdPrices {
+    prices: BTreeMap<String, f64>,
+}
+
+impl UsdPrices {
+    /// The stablecoins at 1.0, plus every coin in `path`, a JSON object of coin to USD price.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut prices: BTreeMap<String, f64> = STABLECOINS.iter().map(|coin| (coin.to_string(), 1.0)).collect();
+        if let Some(path) = path {
+            let file = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let file: BTreeMap<String, f64> =
+                serde_json::from_str(&file).with_context(|| format!("Failed to parse {}", path.display()))?;
+            prices.extend(file.into_iter().map(|(coin, price)| (coin.to_uppercase(), price)));
+        }
+
+        Ok(UsdPrices { prices })
+    }
+
+    pub fn price(&self, coin: &str) -> Option<f64> {
+        self.prices.get(&coin.to_uppercase()).copied()
+    }
+
+    /// The contest's award pool in USD, `None` when its coin has no price. A pool without a coin is
+    /// taken to be in USD already, the way the contests page shows it.
+    pub fn award_usd(&self, contest: &Contest) -> Option<f64> {
+        let price = match contest.award_coin.as_deref() {
+            Some(coin) => self.price(coin)?,
+            None => 1.0,
+        };
+        Some(contest.award_pool() as f64 * price)
+    }
+}
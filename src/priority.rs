@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+
+use crate::contests::Contest;
+
+/// Award per SLOC, in the pool's currency, that scores half of the award term.
+const AWARD_PER_SLOC_MIDPOINT: f64 = 100.0;
+/// In-scope SLOC that scores half of the scope term.
+const SLOC_MIDPOINT: f64 = 1000.0;
+/// Days left that score half of the time term.
+const DAYS_LEFT_MIDPOINT: f64 = 3.0;
+/// Multiplier for contests whose code isn't public, they need an approved application first.
+const GATED_FACTOR: f64 = 0.5;
+
+/// What the run measured about a contest, next to what the contests page says.
+#[derive(Debug, Clone, Copy)]
+pub struct ContestMetrics {
+    /// In-scope SLOC across the contest's repos
+    pub sloc: usize,
+    /// The award pool in USD, with `--usd` and a price for the contest's coin
+    pub award_usd: Option<f64>,
+}
+
+/// How much each term counts, see `priority_score`.
+#[derive(Debug, Clone, Copy)]
+pub struct PriorityWeights {
+    pub award: f64,
+    pub scope: f64,
+    pub time: f64,
+}
+
+/// Ranks how attractive a contest is to a warden, higher is better and the result is in `[0, 1]`.
+///
+/// Each term is squashed into `[0, 1)` with `x / (x + midpoint)`, so no other contest is needed to normalize:
+///
+/// - award: `award_pool / sloc`, the pool a line of code is worth, in USD when it's known
+/// - scope: `1 / (1 + sloc / 1000)`, smaller contests are quicker to cover
+/// - time: `days_left / (days_left + 3)`, zero once the contest has ended
+///
+/// The score is the weighted mean of the three, halved when code access isn't public.
+pub fn priority_score(contest: &Contest, metrics: &ContestMetrics, weights: &PriorityWeights) -> f64 {
+    let sloc = metrics.sloc.max(1) as f64;
+    let award_per_sloc = metrics.award_usd.unwrap_or(contest.award_pool() as f64) / sloc;
+    let award = award_per_sloc / (award_per_sloc + AWARD_PER_SLOC_MIDPOINT);
+    let scope = 1.0 / (1.0 + metrics.sloc as f64 / SLOC_MIDPOINT);
+
+    let days_left = contest
+        .end_time
+        .as_deref()
+        .and_then(|end_time| DateTime::parse_from_rfc3339(end_time).ok())
+        .map_or(0.0, |end_time| (end_time.with_timezone(&Utc) - Utc::now()).num_seconds().max(0) as f64 / 86_400.0);
+    let time = days_left / (days_left + DAYS_LEFT_MIDPOINT);
+
+    let total_weight = weights.award + weights.scope + weights.time;
+    if total_weight <= 0.0 {
+        return 0.0;
+    }
+    let score = (weights.award * award + weights.scope * scope + weights.time * time) / total_weight;
+
+    if contest.is_public() { score } else { score * GATED_FACTOR }
+}
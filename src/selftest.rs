@@ -0,0 +1,80 @@
+use code4rena::compile::{compile_contract, get_contracts_bytecodes, CompileSettings};
+use code4rena::contests::parse_contests_html;
+use code4rena::sources::{contract_kinds, import_closure, solidity_pragma, ContractKind};
+
+use std::collections::BTreeMap;
+
+const CONTESTS_PAGE: &str = include_str!("../tests/fixtures/contests.html");
+const COUNTER: &str = include_str!("../tests/fixtures/selftest/Counter.sol");
+const ICOUNTER: &str = include_str!("../tests/fixtures/selftest/ICounter.sol");
+
+/// Runs the contests parser and the compile pipeline on the bundled fixtures, printing pass or fail
+/// for each stage. Nothing touches the network, only the compile stage needs solc.
+pub fn selftest(settings: &CompileSettings) -> anyhow::Result<()> {
+    let repo = BTreeMap::from([
+        ("src/Counter.sol".to_string(), COUNTER.to_string()),
+        ("src/ICounter.sol".to_string(), ICOUNTER.to_string()),
+    ]);
+    let stages = [
+        ("contests page", contests_page()),
+        ("sources", sources(&repo)),
+        ("compile", compile(&repo, settings)),
+    ];
+
+    let mut failed = 0;
+    for (name, outcome) in &stages {
+        match outcome {
+            Ok(detail) => println!("PASS {}: {}", name, detail),
+            Err(err) => {
+                println!("FAIL {}: {}", name, err);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} self-test stages failed", failed, stages.len());
+    }
+    println!("All {} self-test stages passed", stages.len());
+
+    Ok(())
+}
+
+fn contests_page() -> Result<String, String> {
+    let contests = parse_contests_html(CONTESTS_PAGE).map_err(|err| err.to_string())?;
+    if contests.is_empty() {
+        return Err("no contests parsed from the fixture page".to_string());
+    }
+    if let Some(contest) = contests.iter().find(|contest| contest.repos().is_empty()) {
+        return Err(format!("{} has no repo", contest.slug.as_deref().unwrap_or("a contest")));
+    }
+
+    Ok(format!("parsed {} contests", contests.len()))
+}
+
+fn sources(repo: &BTreeMap<String, String>) -> Result<String, String> {
+    let closure = import_closure("src/Counter.sol", repo);
+    if closure.len() != 2 {
+        return Err(format!("expected Counter.sol and the ICounter.sol it imports, resolved {} files", closure.len()));
+    }
+    if solidity_pragma(COUNTER).as_deref() != Some("^0.8.0") {
+        return Err("pragma solidity ^0.8.0 not found".to_string());
+    }
+    let kinds = contract_kinds(COUNTER).into_iter().chain(contract_kinds(ICOUNTER)).collect::<BTreeMap<_, _>>();
+    if kinds.get("Counter") != Some(&ContractKind::Concrete) || kinds.get("ICounter") != Some(&ContractKind::Interface) {
+        return Err(format!("unexpected declarations {:?}", kinds));
+    }
+
+    Ok("resolved imports, pragma and declarations".to_string())
+}
+
+fn compile(repo: &BTreeMap<String, String>, settings: &CompileSettings) -> Result<String, String> {
+    let output = compile_contract(import_closure("src/Counter.sol", repo), settings).map_err(|err| err.to_string())?;
+    if let Some(error) = output.errors.iter().find(|error| error.severity.is_error()) {
+        return Err(error.message.clone());
+    }
+    let bytecodes = get_contracts_bytecodes(&output.contracts, "src/Counter.sol").unwrap_or_default();
+    match bytecodes.iter().find(|(name, _)| name == "Counter") {
+        Some((_, bytecode)) if !bytecode.is_empty() => Ok(format!("Counter compiled to {} bytes", bytecode.len() / 2)),
+        _ => Err("no bytecode for Counter".to_string()),
+    }
+}
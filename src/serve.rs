@@ -0,0 +1,95 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use code4rena::output::{ContestResult, ContractRecord};
+
+use crate::shutdown;
+
+/// The latest refresh's results, swapped in whole once a scrape finishes.
+#[derive(Default)]
+pub struct Snapshot {
+    pub generated_at: Option<String>,
+    pub contests: Vec<ContestResult>,
+}
+
+pub type SharedSnapshot = Arc<RwLock<Snapshot>>;
+
+/// A contest without its files, `/contests/:id/contracts` has those.
+#[derive(Debug, Serialize)]
+struct ContestSummary<'a> {
+    key: String,
+    contest_id: Option<u32>,
+    slug: Option<&'a str>,
+    sponsor: Option<&'a str>,
+    repo: Option<&'a str>,
+    award: Option<&'a str>,
+    award_usd: Option<f64>,
+    end_time: Option<&'a str>,
+    files: usize,
+    in_scope_contracts: usize,
+    sloc: usize,
+    priority: Option<f64>,
+    error: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct ContestsResponse<'a> {
+    /// When the results were scraped, `None` until the first refresh finishes
+    generated_at: Option<&'a str>,
+    contests: Vec<ContestSummary<'a>>,
+}
+
+/// Answers `/contests` and `/contests/:id/contracts` from `snapshot` until a signal stops the run.
+pub async fn serve(listen: SocketAddr, snapshot: SharedSnapshot) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/contests", get(contests))
+        .route("/contests/:id/contracts", get(contracts))
+        .with_state(snapshot);
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    println!("Serving contests on http://{}", listener.local_addr()?);
+    axum::serve(listener, app).with_graceful_shutdown(shutdown::wait()).await?;
+
+    Ok(())
+}
+
+async fn contests(State(snapshot): State<SharedSnapshot>) -> Json<serde_json::Value> {
+    let snapshot = snapshot.read().await;
+    let contests = snapshot
+        .contests
+        .iter()
+        .map(|contest| ContestSummary {
+            key: contest.key(),
+            contest_id: contest.contest_id,
+            slug: contest.slug.as_deref(),
+            sponsor: contest.sponsor.as_deref(),
+            repo: contest.repo.as_deref(),
+            award: contest.award.as_deref(),
+            award_usd: contest.award_usd,
+            end_time: contest.end_time.as_deref(),
+            files: contest.files.len(),
+            in_scope_contracts: contest.in_scope_contracts,
+            sloc: contest.sloc(),
+            priority: contest.priority,
+            error: contest.error.as_deref(),
+        })
+        .collect();
+
+    Json(serde_json::json!(ContestsResponse { generated_at: snapshot.generated_at.as_deref(), contests }))
+}
+
+/// Every contract of the contest whose key (its id, or slug when it has none) is `id`.
+async fn contracts(State(snapshot): State<SharedSnapshot>, Path(id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let snapshot = snapshot.read().await;
+    let contest = snapshot.contests.iter().find(|contest| contest.key() == id).ok_or(StatusCode::NOT_FOUND)?;
+    let records: Vec<ContractRecord> = ContractRecord::all(contest, &contest.files).collect();
+
+    Ok(Json(serde_json::json!(records)))
+}
@@ -0,0 +1,57 @@
+use tokio::sync::Notify;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit code of a run cut short by SIGINT or SIGTERM, `128 + SIGINT` like a shell reports.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Notify = Notify::const_new();
+
+/// Listens for SIGINT and SIGTERM, the first asks the run to wind down and a second exits straight away.
+pub fn install_handler() {
+    tokio::spawn(async {
+        wait_for_signal().await;
+        REQUESTED.store(true, Ordering::SeqCst);
+        NOTIFY.notify_waiters();
+        eprintln!("Interrupted, finishing in-flight work and writing partial results, interrupt again to exit now");
+
+        wait_for_signal().await;
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    });
+}
+
+/// Whether a signal asked the run to stop starting new work.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Resolves once a signal asked the run to stop.
+pub async fn wait() {
+    let notified = NOTIFY.notified();
+    if !requested() {
+        notified.await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
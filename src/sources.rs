@@ -0,0 +1,362 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use ethers_solc::artifacts::Source;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// A source extension the scraper can fetch and compile, selected with `--ext`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceExtension {
+    /// Solidity, compiled by solc
+    Sol,
+    /// Standalone Yul objects, compiled by solc in Yul mode
+    Yul,
+    /// Vyper, compiled by the `vyper` binary on PATH
+    Vy,
+}
+
+/// The compiler a source is routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compiler {
+    Solc,
+    Vyper,
+}
+
+impl SourceExtension {
+    pub fn of(path: &str) -> Option<Self> {
+        match path.rsplit_once('.')?.1 {
+            "sol" => Some(SourceExtension::Sol),
+            "yul" => Some(SourceExtension::Yul),
+            "vy" => Some(SourceExtension::Vy),
+            _ => None,
+        }
+    }
+
+    pub fn compiler(self) -> Compiler {
+        match self {
+            SourceExtension::Sol | SourceExtension::Yul => Compiler::Solc,
+            SourceExtension::Vy => Compiler::Vyper,
+        }
+    }
+}
+
+/// Whether `path` has one of the accepted `extensions`.
+pub fn is_source_file(path: &str, extensions: &[SourceExtension]) -> bool {
+    SourceExtension::of(path).is_some_and(|extension| extensions.contains(&extension))
+}
+
+/// Directories holding build output, checkouts or installed packages rather than sources,
+/// `--skip-dir` replaces the set.
+///
+/// `@scope/...` imports resolve through the `lib/` mappings instead of `node_modules/`.
+pub const SKIPPED_DIRS: [&str; 6] = ["out", "artifacts", "cache", "broadcast", ".git", "node_modules"];
+
+/// Whether any directory on `path` is one of `skip_dirs`, at whatever depth.
+pub fn in_skipped_dir(path: &str, skip_dirs: &[String]) -> bool {
+    path.split('/').rev().skip(1).any(|dir| skip_dirs.iter().any(|skipped| skipped == dir))
+}
+
+/// Every source file fetched from a repo keyed by path, plus where they came from.
+#[derive(Debug, Default)]
+pub struct RepoSources {
+    pub files: BTreeMap<String, String>,
+    /// Git blob SHA of each file
+    pub blob_shas: BTreeMap<String, String>,
+    /// GitHub API URL each file was downloaded from, empty when the repo was cloned
+    pub urls: BTreeMap<String, String>,
+    /// Commit the files were read at, when known
+    pub commit: Option<String>,
+    /// The repo's root `foundry.toml`, when it has one
+    pub foundry_toml: Option<String>,
+    /// Path -> error of the files listed in the repo that couldn't be downloaded
+    pub failed: BTreeMap<String, String>,
+}
+
+impl RepoSources {
+    /// Whether the repo's default foundry profile compiles with `via_ir`.
+    pub fn foundry_via_ir(&self) -> bool {
+        let Some(config) = self.foundry_toml.as_deref().and_then(|config| config.parse::<toml::Table>().ok()) else {
+            return false;
+        };
+        let profile = &config.get("profile").and_then(|profile| profile.get("default"));
+        profile
+            .and_then(|profile| profile.get("via_ir").or_else(|| profile.get("via-ir")))
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false)
+    }
+}
+
+/// Top level directories holding vendored dependencies, fed to solc but never reported as targets.
+const DEPENDENCY_DIRS: [&str; 2] = ["lib", "node_modules"];
+
+/// Directories holding tests and deploy scripts rather than audit targets.
+const TEST_DIRS: [&str; 3] = ["test", "tests", "script"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Target,
+    Dependency,
+    Test,
+}
+
+impl Scope {
+    pub fn of(path: &str) -> Self {
+        let mut directories = path.split('/').rev().skip(1);
+        let top_level = path.split_once('/').map(|(dir, _)| dir);
+        if top_level.is_some_and(|dir| DEPENDENCY_DIRS.contains(&dir)) || directories.clone().any(|dir| dir == "node_modules") {
+            Scope::Dependency
+        } else if directories.any(|dir| TEST_DIRS.contains(&dir)) || path.ends_with(".t.sol") || path.ends_with(".s.sol") {
+            Scope::Test
+        } else {
+            Scope::Target
+        }
+    }
+
+    pub fn is_target(self) -> bool {
+        self == Scope::Target
+    }
+}
+
+/// What a contract declaration is, an interface compiles to empty bytecode without anything being wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContractKind {
+    Interface,
+    Abstract,
+    Library,
+    Concrete,
+}
+
+impl ContractKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContractKind::Interface => "interface",
+            ContractKind::Abstract => "abstract",
+            ContractKind::Library => "library",
+            ContractKind::Concrete => "concrete",
+        }
+    }
+}
+
+fn declaration_regex() -> &'static Regex {
+    static DECLARATION: OnceLock<Regex> = OnceLock::new();
+    DECLARATION.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(abstract\s+contract|contract|interface|library)\s+([A-Za-z_$][A-Za-z0-9_$]*)").unwrap()
+    })
+}
+
+/// The kind of every contract, interface and library declared in a source file, by name.
+pub fn contract_kinds(source: &str) -> BTreeMap<String, ContractKind> {
+    declaration_regex()
+        .captures_iter(source)
+        .map(|declaration| {
+            let kind = match &declaration[1] {
+                "interface" => ContractKind::Interface,
+                "library" => ContractKind::Library,
+                "contract" => ContractKind::Concrete,
+                _ => ContractKind::Abstract,
+            };
+            (declaration[2].to_string(), kind)
+        })
+        .collect()
+}
+
+fn import_regex() -> &'static Regex {
+    static IMPORT: OnceLock<Regex> = OnceLock::new();
+    IMPORT.get_or_init(|| Regex::new(r#"import\s+(?:[^'";]*\s+from\s+)?["']([^"']+)["']"#).unwrap())
+}
+
+/// Collapses `.` and `..` segments the way solc normalizes relative imports.
+fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Maps the source unit name solc will ask for onto a path in the repo.
+///
+/// Non-relative imports (`forge-std/Test.sol`, `@openzeppelin/contracts/...`) are looked up the way
+/// foundry's `lib/` remappings map them first, then by matching the longest path suffix.
+fn resolve<'a>(unit_name: &str, repo: &'a BTreeMap<String, String>) -> Option<&'a String> {
+    if let Some((path, _)) = repo.get_key_value(unit_name) {
+        return Some(path);
+    }
+
+    if let Some((package, rest)) = unit_name.split_once('/') {
+        let candidates = [
+            format!("lib/{}/src/{}", package, rest),
+            format!("lib/{}/{}", package, rest),
+        ];
+        if let Some((path, _)) = candidates.iter().find_map(|candidate| repo.get_key_value(candidate)) {
+            return Some(path);
+        }
+    }
+
+    let mut suffix = unit_name;
+    while let Some((_, rest)) = suffix.split_once('/') {
+        if !rest.contains('/') {
+            break;
+        }
+        let needle = format!("/{}", rest);
+        if let Some(path) = repo.keys().find(|path| path.ends_with(&needle)) {
+            return Some(path);
+        }
+        suffix = rest;
+    }
+
+    None
+}
+
+fn pragma_regex() -> &'static Regex {
+    static PRAGMA: OnceLock<Regex> = OnceLock::new();
+    PRAGMA.get_or_init(|| Regex::new(r"pragma\s+solidity\s+([^;]+);").unwrap())
+}
+
+/// The `pragma solidity` version constraint of a source file, whitespace normalized.
+pub fn solidity_pragma(source: &str) -> Option<String> {
+    let constraint = pragma_regex().captures(source)?;
+    Some(constraint[1].split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Every `pragma solidity` constraint of a source file in order, flattened files can hold several.
+pub fn solidity_pragmas(source: &str) -> Vec<String> {
+    pragma_regex()
+        .captures_iter(source)
+        .map(|constraint| constraint[1].split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+/// Whether a pragma pins one compiler version, code4rena checklists flag floating ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PragmaLock {
+    /// An exact version, `0.8.19` or `=0.8.19`
+    Locked,
+    /// Any range, `^0.8.0`, `>=0.8.0 <0.9.0`, `~0.8.0`, ...
+    Floating,
+}
+
+impl PragmaLock {
+    /// Classifies a constraint as `solidity_pragma` gives it.
+    pub fn of(constraint: &str) -> Self {
+        let version = constraint.strip_prefix('=').unwrap_or(constraint).trim();
+        let exact = !version.is_empty() && version.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if exact { PragmaLock::Locked } else { PragmaLock::Floating }
+    }
+}
+
+/// Groups files by their pragma constraint, files without a pragma are left out.
+pub fn pragma_report<'a>(files: impl IntoIterator<Item = (&'a String, &'a String)>) -> BTreeMap<String, Vec<&'a str>> {
+    let mut report: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (path, source) in files {
+        if let Some(pragma) = solidity_pragma(source) {
+            report.entry(pragma).or_default().push(path);
+        }
+    }
+
+    report
+}
+
+/// Source lines of code, lines holding anything besides whitespace and comments.
+pub fn sloc(source: &str) -> usize {
+    let mut in_comment = false;
+    source
+        .lines()
+        .filter(|line| {
+            let mut rest = line.trim();
+            let mut has_code = false;
+            while !rest.is_empty() {
+                if in_comment {
+                    match rest.find("*/") {
+                        Some(end) => {
+                            in_comment = false;
+                            rest = rest[end + 2..].trim_start();
+                        }
+                        None => break,
+                    }
+                } else if rest.starts_with("//") {
+                    break;
+                } else if let Some(comment) = rest.strip_prefix("/*") {
+                    in_comment = true;
+                    rest = comment;
+                } else {
+                    has_code = true;
+                    match [rest.find("/*"), rest.find("//")].into_iter().flatten().min() {
+                        Some(comment) => rest = &rest[comment..],
+                        None => break,
+                    }
+                }
+            }
+            has_code
+        })
+        .count()
+}
+
+fn comment_regex() -> &'static Regex {
+    static COMMENT: OnceLock<Regex> = OnceLock::new();
+    COMMENT.get_or_init(|| Regex::new(r"(?s)/\*.*?\*/|//[^\n]*").unwrap())
+}
+
+fn declaration_keyword_regex() -> &'static Regex {
+    static KEYWORD: OnceLock<Regex> = OnceLock::new();
+    KEYWORD.get_or_init(|| Regex::new(r"\b(contract|interface|library|function)\b").unwrap())
+}
+
+/// Whether a Solidity source declares nothing to compile, no `contract`, `interface`, `library` or
+/// `function` outside of comments. Empty files and ones holding only a license header or pragma are.
+pub fn is_empty_source(source: &str) -> bool {
+    !declaration_keyword_regex().is_match(&comment_regex().replace_all(source, ""))
+}
+
+/// The `SPDX-License-Identifier` declared in a source file, if any.
+pub fn spdx_license(source: &str) -> Option<String> {
+    source.lines().find_map(|line| {
+        let (_, license) = line.split_once("SPDX-License-Identifier:")?;
+        let license = license.trim().trim_end_matches("*/").trim();
+        (!license.is_empty()).then(|| license.to_string())
+    })
+}
+
+/// Collects `target` and everything it transitively imports, keyed by the unit names solc expects.
+///
+/// Imports that can't be found in `repo` are left out, solc reports them when compiling.
+pub fn import_closure(target: &str, repo: &BTreeMap<String, String>) -> BTreeMap<PathBuf, Source> {
+    let mut sources = BTreeMap::new();
+    let mut queue = VecDeque::from([target.to_string()]);
+
+    while let Some(unit_name) = queue.pop_front() {
+        if sources.contains_key(&PathBuf::from(&unit_name)) {
+            continue;
+        }
+        let Some(content) = resolve(&unit_name, repo).and_then(|path| repo.get(path)) else {
+            continue;
+        };
+
+        for import in import_regex().captures_iter(content) {
+            let import = &import[1];
+            let imported = if import.starts_with("./") || import.starts_with("../") {
+                let directory = unit_name.rsplit_once('/').map_or("", |(directory, _)| directory);
+                normalize(&format!("{}/{}", directory, import))
+            } else {
+                import.to_string()
+            };
+            queue.push_back(imported);
+        }
+
+        sources.insert(PathBuf::from(unit_name), Source::new(content.as_str()));
+    }
+
+    sources
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::output::FileResult;
+
+/// What earlier runs saw, kept in `--state-file` between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunState {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Last commit processed for each repo URL
+    #[serde(default)]
+    pub commits: BTreeMap<String, String>,
+    /// Contest key -> repo URL -> commit of every contest whose repos were all processed, for `--skip-existing`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contests: BTreeMap<String, BTreeMap<String, String>>,
+    /// Contest key -> path -> blob SHA of every file whose output was produced, lets a crashed run resume
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub completed: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl RunState {
+    /// Loads the state at `path`, starting empty when the file doesn't exist yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut state: RunState = match std::fs::read_to_string(path) {
+            Ok(state) => serde_json::from_str(&state)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => RunState::default(),
+            Err(err) => return Err(err.into()),
+        };
+        state.path = path.to_path_buf();
+
+        Ok(state)
+    }
+
+    /// Path -> blob SHA of the files of `contest` an earlier run produced output for.
+    pub fn completed_files(&self, contest: &str) -> BTreeMap<String, String> {
+        self.completed.get(contest).cloned().unwrap_or_default()
+    }
+
+    /// Records `files` as done, the ones without a blob SHA can't be matched on resume and are left out.
+    pub fn complete_files(&mut self, contest: &str, files: &[FileResult]) {
+        let completed = self.completed.entry(contest.to_string()).or_default();
+        for file in files {
+            if let Some(blob_sha) = &file.blob_sha {
+                completed.insert(file.path.clone(), blob_sha.clone());
+            }
+        }
+    }
+
+    /// Whether an earlier run processed every repo of `contest` at exactly these `commits`.
+    pub fn contest_done(&self, contest: &str, commits: &BTreeMap<String, String>) -> bool {
+        self.contests.get(contest) == Some(commits)
+    }
+
+    /// Records every repo of `contest` as processed at `commits`.
+    pub fn complete_contest(&mut self, contest: &str, commits: &BTreeMap<String, String>) {
+        self.contests.insert(contest.to_string(), commits.clone());
+    }
+
+    /// Replaces the state file atomically, an interrupted run leaves the previous state intact.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut file = tempfile::NamedTempFile::new_in(directory)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.flush()?;
+        file.persist(&self.path)?;
+
+        Ok(())
+    }
+}
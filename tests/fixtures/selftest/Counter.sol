@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import "./ICounter.sol";
+
+contract Counter is ICounter {
+    uint256 public count;
+
+    function increment() external {
+        count += 1;
+    }
+}